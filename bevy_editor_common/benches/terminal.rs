@@ -0,0 +1,58 @@
+use bevy_editor_common::egui_27::TerminalView;
+use bevy_editor_common::vt;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use egui_0_27::{CentralPanel, Context, RawInput};
+
+fn log_terminal(lines: usize) -> vt::Terminal {
+    let mut terminal = vt::Terminal::new(vt::DEFAULT_COLUMNS, vt::DEFAULT_ROWS, lines + 1);
+    for i in 0..lines {
+        terminal.advance(
+            format!(
+                "\x1b[2m2024-05-01T12:00:00Z\x1b[0m \x1b[32m INFO\x1b[0m \x1b[2mbevy_render\x1b[0m: line {i}\n"
+            )
+            .as_bytes(),
+        );
+    }
+    terminal
+}
+
+fn frame(ctx: &Context, view: &mut TerminalView, terminal: &vt::Terminal) {
+    let _ = ctx.run(RawInput::default(), |ctx| {
+        CentralPanel::default().show(ctx, |ui| view.rows(terminal, ui));
+    });
+}
+
+fn render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("terminal frame");
+    for lines in [1_000, 10_000, 100_000] {
+        let terminal = log_terminal(lines);
+        let ctx = Context::default();
+        let mut view = TerminalView::default();
+        // let the scroll area settle at the bottom before measuring
+        frame(&ctx, &mut view, &terminal);
+        frame(&ctx, &mut view, &terminal);
+        group.bench_with_input(BenchmarkId::from_parameter(lines), &terminal, |b, terminal| {
+            b.iter(|| frame(&ctx, &mut view, terminal))
+        });
+    }
+    group.finish();
+}
+
+fn append(c: &mut Criterion) {
+    let mut group = c.benchmark_group("terminal append 100 lines");
+    for lines in [1_000, 10_000, 100_000] {
+        let mut terminal = log_terminal(lines);
+        terminal.set_scrollback(lines);
+        group.bench_function(BenchmarkId::from_parameter(lines), |b| {
+            b.iter(|| {
+                for i in 0..100 {
+                    terminal.advance(format!("\x1b[33m WARN\x1b[0m appended {i}\n").as_bytes());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, render, append);
+criterion_main!(benches);
@@ -0,0 +1,125 @@
+//! A pretend language server for the `lsp` client's tests. It answers
+//! `initialize`, publishes the text of every opened or changed document as a
+//! diagnostic, and gives canned hovers, definitions and completions.
+//!
+//! With `--stop-reading` it publishes a diagnostic for `stopped-reading`
+//! once initialized and then never reads again, like a busy server.
+//!
+//! Cargo finds it on its own rather than through `Cargo.toml`, so the copies
+//! of this crate the project manager makes can leave it out.
+
+// without the `lsp` feature there's nothing to test, and no serde_json
+#[cfg(not(feature = "lsp"))]
+fn main() {}
+
+#[cfg(feature = "lsp")]
+fn main() {
+    stub::main();
+}
+
+#[cfg(feature = "lsp")]
+mod stub {
+    use serde_json::{json, Value};
+    use std::io::{self, BufRead, Write};
+
+    fn read(stdin: &mut impl BufRead) -> Option<Value> {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if stdin.read_line(&mut header).ok()? == 0 {
+                return None;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+        let mut body = vec![0; length?];
+        stdin.read_exact(&mut body).ok()?;
+        serde_json::from_slice(&body).ok()
+    }
+
+    fn write(message: Value) {
+        let body = message.to_string();
+        let mut stdout = io::stdout().lock();
+        write!(stdout, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        stdout.flush().unwrap();
+    }
+
+    fn publish(uri: &Value, version: &Value, text: &Value) {
+        write(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {
+                "uri": uri,
+                "diagnostics": [{
+                    "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } },
+                    "severity": 2,
+                    "source": "stub",
+                    "message": format!("v{version}: {}", text.as_str().unwrap_or_default()),
+                }],
+            },
+        }));
+    }
+
+    pub fn main() {
+        let stop_reading = std::env::args().any(|arg| arg == "--stop-reading");
+        let mut stdin = io::stdin().lock();
+        let mut initialized = false;
+        while let Some(message) = read(&mut stdin) {
+            let id = message.get("id").cloned();
+            let params = &message["params"];
+            let method = message["method"].as_str().unwrap_or_default();
+            // everything but `initialize` has to wait for `initialized`
+            if !initialized && !matches!(method, "initialize" | "initialized") {
+                panic!("{method} before initialized");
+            }
+            let result = match method {
+                "initialize" => json!({ "capabilities": { "hoverProvider": true } }),
+                "initialized" if stop_reading => {
+                    publish(&json!("file:///project/stopped-reading"), &json!(0), &json!(""));
+                    std::thread::sleep(std::time::Duration::from_secs(60));
+                    return;
+                }
+                "initialized" => {
+                    initialized = true;
+                    continue;
+                }
+                "textDocument/didOpen" => {
+                    let document = &params["textDocument"];
+                    publish(&document["uri"], &document["version"], &document["text"]);
+                    continue;
+                }
+                "textDocument/didChange" => {
+                    let document = &params["textDocument"];
+                    publish(&document["uri"], &document["version"], &params["contentChanges"][0]["text"]);
+                    continue;
+                }
+                "textDocument/hover" => {
+                    let position = &params["position"];
+                    json!({ "contents": {
+                        "kind": "plaintext",
+                        "value": format!("hover at {}:{}", position["line"], position["character"]),
+                    }})
+                }
+                "textDocument/definition" => json!([{
+                    "uri": params["textDocument"]["uri"],
+                    "range": { "start": params["position"], "end": params["position"] },
+                }]),
+                "textDocument/completion" => json!({
+                    "isIncomplete": false,
+                    "items": [{ "label": "stub_item", "detail": "from the stub", "insertText": "stub_item()" }],
+                }),
+                "shutdown" => Value::Null,
+                "exit" => return,
+                _ => continue,
+            };
+            if let Some(id) = id {
+                write(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+            }
+        }
+    }
+}
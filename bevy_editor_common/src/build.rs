@@ -0,0 +1,122 @@
+//! Following cargo's builds in a process' output.
+
+use crate::protocol::BuildEvent;
+
+/// Watches output for cargo starting and finishing a build. Fed whatever
+/// chunks the output arrives in, lines can be split across them.
+#[derive(Debug, Default)]
+pub struct BuildTracker {
+    line: Vec<u8>,
+    building: bool,
+}
+
+impl BuildTracker {
+    pub fn is_building(&self) -> bool {
+        self.building
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<BuildEvent> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            // the progress bar redraws itself with `\r`
+            if byte == b'\n' || byte == b'\r' {
+                let line = std::mem::take(&mut self.line);
+                events.extend(self.line_done(&strip_escapes(&line)));
+            } else {
+                self.line.push(byte);
+            }
+        }
+        events
+    }
+
+    /// The process is gone, a build it was in the middle of didn't make it.
+    pub fn exited(&mut self) -> Option<BuildEvent> {
+        self.line.clear();
+        std::mem::take(&mut self.building).then_some(BuildEvent::Failed)
+    }
+
+    fn line_done(&mut self, line: &str) -> Option<BuildEvent> {
+        let line = line.trim_start();
+        if !self.building && (line.starts_with("Compiling ") || line.starts_with("Checking ")) {
+            self.building = true;
+            return Some(BuildEvent::Started);
+        }
+        if self.building && line.starts_with("Finished ") {
+            self.building = false;
+            return Some(BuildEvent::Succeeded);
+        }
+        if self.building && line.starts_with("error: could not compile") {
+            self.building = false;
+            return Some(BuildEvent::Failed);
+        }
+        None
+    }
+}
+
+/// Drops the colors and cursor movement cargo sprinkles its lines with.
+fn strip_escapes(line: &[u8]) -> String {
+    let mut text = Vec::with_capacity(line.len());
+    let mut bytes = line.iter().copied();
+    while let Some(byte) = bytes.next() {
+        if byte != 0x1b {
+            text.push(byte);
+            continue;
+        }
+        // CSI: ESC [ parameters final byte
+        if bytes.next() == Some(b'[') {
+            for byte in bytes.by_ref() {
+                if (0x40..=0x7e).contains(&byte) {
+                    break;
+                }
+            }
+        }
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use BuildEvent::{Failed, Started, Succeeded};
+
+    fn events(chunks: &[&str]) -> Vec<BuildEvent> {
+        let mut tracker = BuildTracker::default();
+        chunks
+            .iter()
+            .flat_map(|chunk| tracker.feed(chunk.as_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn transitions() {
+        let cases: [(&[&str], &[BuildEvent]); 8] = [
+            (&["   Compiling game v0.1.0\n    Finished dev\n"], &[Started, Succeeded]),
+            (&["    Checking game v0.1.0\nerror: could not compile `game`\n"], &[Started, Failed]),
+            // one build compiles many crates
+            (&["   Compiling a\n   Compiling b\n    Finished dev\n"], &[Started, Succeeded]),
+            (&["   Compiling a\n    Finished dev\n   Compiling a\n    Finished dev\n"], &[Started, Succeeded, Started, Succeeded]),
+            // nothing to finish or fail without a start
+            (&["    Finished dev\nerror: could not compile `game`\n"], &[]),
+            // split across chunks
+            (&["   Compi", "ling game\n    Fin", "ished dev", "\n"], &[Started, Succeeded]),
+            // colored, and the progress bar redrawing itself
+            (&["\x1b[1m\x1b[32m   Compiling\x1b[0m game\n\x1b[1m\x1b[36m    Building\x1b[0m [==> ] 3/4\r\x1b[K\x1b[1m\x1b[32m    Finished\x1b[0m dev\n"], &[Started, Succeeded]),
+            (&["   Compiling game"], &[]),
+        ];
+        for (chunks, expected) in cases {
+            assert_eq!(events(chunks), expected, "{chunks:?}");
+        }
+    }
+
+    #[test]
+    fn exiting_mid_build_fails_it() {
+        let mut tracker = BuildTracker::default();
+        assert_eq!(tracker.exited(), None);
+        tracker.feed(b"   Compiling game\n    Building [=> ] 1/4");
+        assert!(tracker.is_building());
+        assert_eq!(tracker.exited(), Some(Failed));
+        assert!(!tracker.is_building());
+        // what was left of the line is gone with the process
+        assert_eq!(tracker.feed(b"\n"), []);
+    }
+}
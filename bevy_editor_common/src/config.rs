@@ -0,0 +1,66 @@
+//! The options a project keeps for its editor in [`CONFIG_FILE`], read by the
+//! game and edited from the project manager.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Where a project keeps its editor options, relative to the project directory.
+pub const CONFIG_FILE: &str = ".bevy_editor/editor.ron";
+
+/// Where the editor saves scenes, relative to the project directory.
+pub const SCENES_DIR: &str = "assets/scenes";
+
+/// Where the editor shows up.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum WindowPlacement {
+    /// Over the game, in its window.
+    #[default]
+    GameWindow,
+    /// In a window of its own.
+    NewWindow,
+    /// Borderless fullscreen on the monitor with this index, 0 is the first one.
+    Fullscreen { monitor: usize },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct EditorConfig {
+    pub window: WindowPlacement,
+    /// Windows to leave out, by their tab title (`"Renderer"`, `"Cameras"`...).
+    pub without: Vec<String>,
+    /// The scene in [`SCENES_DIR`] the game starts with, named without its
+    /// `.scn.ron`. Without one the game's own setup builds the world.
+    pub scene: Option<String>,
+}
+
+impl EditorConfig {
+    pub fn path(project_dir: &Path) -> PathBuf {
+        project_dir.join(CONFIG_FILE)
+    }
+
+    /// Where the scene called `name` is saved.
+    pub fn scene_path(project_dir: &Path, name: &str) -> PathBuf {
+        project_dir.join(SCENES_DIR).join(format!("{name}.scn.ron"))
+    }
+
+    /// No config is the same as an empty one, one that doesn't parse is an error.
+    pub fn load(project_dir: &Path) -> Result<EditorConfig, String> {
+        let path = Self::path(project_dir);
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return Ok(EditorConfig::default());
+        };
+        ron::from_str(&text)
+            .map_err(|err| format!("couldn't read the editor config from {}: {err}", path.display()))
+    }
+
+    pub fn save(&self, project_dir: &Path) -> Result<(), String> {
+        let path = Self::path(project_dir);
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        std::fs::write(&path, text)
+            .map_err(|err| format!("couldn't save the editor config to {}: {err}", path.display()))
+    }
+}
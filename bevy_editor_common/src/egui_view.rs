@@ -0,0 +1,693 @@
+// Compiled once per supported egui version, see `lib.rs`.
+
+use crate::failure::Location;
+use crate::log::Level;
+use crate::process::OutputReceiver;
+use crate::vt;
+use egui::text::LayoutJob;
+use egui::{
+    Align, Color32, CursorIcon, DragValue, FontId, Label, Layout, ScrollArea, Sense, Stroke,
+    TextEdit, TextFormat, Ui,
+};
+use regex::{Regex, RegexBuilder};
+use std::collections::VecDeque;
+use std::ops::Range;
+
+pub fn from_vt_to_egui_color(color: vt::Color, default: Color32) -> Color32 {
+    match color.to_rgb() {
+        Some([r, g, b]) => Color32::from_rgb(r, g, b),
+        None => default,
+    }
+}
+
+pub fn terminal_font() -> FontId {
+    FontId::monospace(12.0)
+}
+
+fn text_format(style: &vt::Style, ui: &Ui) -> TextFormat {
+    let default_fg = if style.bold {
+        ui.visuals().strong_text_color()
+    } else {
+        ui.visuals().text_color()
+    };
+    let mut fg = from_vt_to_egui_color(style.fg, default_fg);
+    let mut bg = from_vt_to_egui_color(style.bg, Color32::TRANSPARENT);
+    if style.inverse {
+        std::mem::swap(&mut fg, &mut bg);
+        if fg == Color32::TRANSPARENT {
+            fg = ui.visuals().extreme_bg_color;
+        }
+    }
+    if style.dim {
+        fg = fg.gamma_multiply(0.6);
+    }
+    TextFormat {
+        font_id: terminal_font(),
+        color: fg,
+        background: bg,
+        italics: style.italic,
+        underline: if style.underline {
+            Stroke::new(1.0, fg)
+        } else {
+            Stroke::NONE
+        },
+        strikethrough: if style.strikethrough {
+            Stroke::new(1.0, fg)
+        } else {
+            Stroke::NONE
+        },
+        ..Default::default()
+    }
+}
+
+pub fn line_layout_job(line: &vt::Line, ui: &Ui) -> LayoutJob {
+    highlighted_line_layout_job(line, ui, &[], None, &[])
+}
+
+/// `highlights` are char ranges (as returned by [`TerminalView`]'s search),
+/// `current` is the one that gets the stronger highlight color. `links` are
+/// char ranges that get underlined.
+fn highlighted_line_layout_job(
+    line: &vt::Line,
+    ui: &Ui,
+    highlights: &[Range<usize>],
+    current: Option<&Range<usize>>,
+    links: &[Range<usize>],
+) -> LayoutJob {
+    let highlight = |col: usize| {
+        if current.is_some_and(|range| range.contains(&col)) {
+            Some(Color32::from_rgb(255, 150, 0))
+        } else if highlights.iter().any(|range| range.contains(&col)) {
+            Some(Color32::from_rgb(120, 90, 0))
+        } else {
+            None
+        }
+    };
+    let mut job = LayoutJob::default();
+    let mut text = String::new();
+    let mut current_style = line.first().map(|cell| cell.style).unwrap_or_default();
+    let link = |col: usize| links.iter().any(|range| range.contains(&col));
+    let format = |style: &vt::Style, highlight: Option<Color32>, link: bool| {
+        let mut format = text_format(style, ui);
+        if let Some(color) = highlight {
+            format.background = color;
+        }
+        if link {
+            format.underline = Stroke::new(1.0, format.color);
+        }
+        format
+    };
+    let mut current_highlight = highlight(0);
+    let mut current_link = link(0);
+    for (col, cell) in line.iter().enumerate() {
+        let cell_highlight = highlight(col);
+        let cell_link = link(col);
+        if cell.style != current_style || cell_highlight != current_highlight || cell_link != current_link {
+            job.append(&text, 0.0, format(&current_style, current_highlight, current_link));
+            text.clear();
+            current_style = cell.style;
+            current_highlight = cell_highlight;
+            current_link = cell_link;
+        }
+        text.push(cell.c);
+    }
+    job.append(&text, 0.0, format(&current_style, current_highlight, current_link));
+    job
+}
+
+/// A prompt for the process' stdin, with shell-like history on up/down.
+#[derive(Default)]
+pub struct InputLine {
+    text: String,
+    history: Vec<String>,
+    history_index: Option<usize>,
+}
+
+impl InputLine {
+    /// Returns the submitted line, without the trailing newline.
+    pub fn show(&mut self, ui: &mut Ui) -> Option<String> {
+        let mut submitted = None;
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(">").font(terminal_font()));
+            let response = ui.add(
+                TextEdit::singleline(&mut self.text)
+                    .font(terminal_font())
+                    .hint_text("stdin")
+                    .desired_width(f32::INFINITY),
+            );
+            if response.has_focus() && !self.history.is_empty() {
+                let (up, down) = ui.input(|input| {
+                    (
+                        input.key_pressed(egui::Key::ArrowUp),
+                        input.key_pressed(egui::Key::ArrowDown),
+                    )
+                });
+                if up {
+                    let index = match self.history_index {
+                        Some(index) => index.saturating_sub(1),
+                        None => self.history.len() - 1,
+                    };
+                    self.history_index = Some(index);
+                    self.text = self.history[index].clone();
+                }
+                if down {
+                    match self.history_index {
+                        Some(index) if index + 1 < self.history.len() => {
+                            self.history_index = Some(index + 1);
+                            self.text = self.history[index + 1].clone();
+                        }
+                        _ => {
+                            self.history_index = None;
+                            self.text.clear();
+                        }
+                    }
+                }
+            }
+            if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                let line = std::mem::take(&mut self.text);
+                if !line.is_empty() && self.history.last() != Some(&line) {
+                    self.history.push(line.clone());
+                }
+                self.history_index = None;
+                response.request_focus();
+                submitted = Some(line);
+            }
+        });
+        submitted
+    }
+}
+
+/// Search, level filtering, selection and saving on top of a [`vt::Terminal`].
+///
+/// Everything is indexed by absolute line number (see
+/// [`vt::Terminal::first_line_number`]) and only the lines that can still change
+/// get rescanned each frame, so this stays cheap on huge logs.
+pub struct TerminalView {
+    search: String,
+    use_regex: bool,
+    case_sensitive: bool,
+    regex: Option<Regex>,
+    search_error: Option<String>,
+    min_level: Option<Level>,
+    /// `(line, chars)` of every match, sorted.
+    matches: VecDeque<(usize, Range<usize>)>,
+    current_match: Option<usize>,
+    /// Absolute numbers of the lines that pass the level filter, sorted.
+    visible: VecDeque<usize>,
+    /// Level of each line, starting at `levels_start`. Lines without a prefix
+    /// inherit the previous one so multi-line records (panics...) stay together.
+    levels: VecDeque<Option<Level>>,
+    levels_start: usize,
+    scanned: usize,
+    needs_rescan: bool,
+    selection: Option<(usize, usize)>,
+    /// Whether a row was clicked since the last press anywhere else, and
+    /// whether the pointer was over the rows last frame. Copy only goes to the
+    /// selection then, it belongs to whatever else has focus otherwise.
+    focused: bool,
+    hovered: bool,
+    /// A `file:line:column` that was clicked, for whoever shows the view to open.
+    clicked_location: Option<Location>,
+    scroll_to_line: Option<usize>,
+    /// Whether the rows were scrolled all the way down last frame. While they are,
+    /// new output keeps them there; scrolling up stops that until you come back down.
+    at_bottom: bool,
+    jump_to_bottom: bool,
+    /// Everything before this line was on screen at some point, the rest is unread.
+    seen_end: usize,
+    /// Output that arrived while paused, fed to the terminal on resume.
+    held: Option<Held>,
+    save_path: String,
+    save_status: Option<String>,
+    input: InputLine,
+}
+
+/// Output held back while paused, no more lines of it than the scrollback
+/// keeps, the rest would fall off the top on resume anyway.
+#[derive(Default)]
+struct Held {
+    chunks: VecDeque<Vec<u8>>,
+    lines: usize,
+    dropped: usize,
+}
+
+fn newlines(chunk: &[u8]) -> usize {
+    chunk.iter().filter(|&&byte| byte == b'\n').count()
+}
+
+impl Held {
+    fn push(&mut self, chunk: Vec<u8>, scrollback: usize) {
+        self.lines += newlines(&chunk);
+        self.chunks.push_back(chunk);
+        while self.lines > scrollback && self.chunks.len() > 1 {
+            let oldest = newlines(&self.chunks.pop_front().unwrap());
+            self.lines -= oldest;
+            self.dropped += oldest;
+        }
+    }
+}
+
+impl Default for TerminalView {
+    fn default() -> Self {
+        TerminalView {
+            search: String::new(),
+            use_regex: false,
+            case_sensitive: false,
+            regex: None,
+            search_error: None,
+            min_level: None,
+            matches: VecDeque::new(),
+            current_match: None,
+            visible: VecDeque::new(),
+            levels: VecDeque::new(),
+            levels_start: 0,
+            scanned: 0,
+            needs_rescan: true,
+            selection: None,
+            focused: false,
+            hovered: false,
+            clicked_location: None,
+            scroll_to_line: None,
+            at_bottom: true,
+            jump_to_bottom: false,
+            seen_end: 0,
+            held: None,
+            save_path: "terminal.log".to_string(),
+            save_status: None,
+            input: InputLine::default(),
+        }
+    }
+}
+
+impl TerminalView {
+    fn compile_search(&mut self) {
+        self.regex = None;
+        self.search_error = None;
+        if self.search.is_empty() {
+            return;
+        }
+        let pattern = if self.use_regex {
+            self.search.clone()
+        } else {
+            regex::escape(&self.search)
+        };
+        match RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+        {
+            Ok(regex) => self.regex = Some(regex),
+            Err(err) => self.search_error = Some(err.to_string()),
+        }
+    }
+
+    fn sync(&mut self, terminal: &vt::Terminal) {
+        let first = terminal.first_line_number();
+        let end = first + terminal.lines().len();
+        let mut rescan_from = self.scanned.min(first + terminal.screen_top());
+        if self.needs_rescan {
+            self.needs_rescan = false;
+            rescan_from = first;
+        }
+        let rescan_from = rescan_from.max(first);
+
+        // forget about lines that fell off the scrollback or are about to be rescanned
+        let dropped = self.matches.partition_point(|(line, _)| *line < first);
+        self.matches.drain(..dropped);
+        self.current_match = self
+            .current_match
+            .and_then(|current| current.checked_sub(dropped));
+        let kept = self.matches.partition_point(|(line, _)| *line < rescan_from);
+        self.matches.truncate(kept);
+        let dropped = self.visible.partition_point(|line| *line < first);
+        self.visible.drain(..dropped);
+        let kept = self.visible.partition_point(|line| *line < rescan_from);
+        self.visible.truncate(kept);
+        if self.levels_start < first {
+            let dropped = (first - self.levels_start).min(self.levels.len());
+            self.levels.drain(..dropped);
+            self.levels_start = first;
+        }
+        self.levels
+            .truncate(rescan_from.saturating_sub(self.levels_start));
+
+        let mut level = self.levels.back().copied().flatten();
+        for number in rescan_from..end {
+            let text = vt::line_text(&terminal.lines()[number - first]);
+            level = Level::detect(&text).or(level);
+            self.levels.push_back(level);
+            let passes_filter = match (self.min_level, level) {
+                (Some(min), Some(level)) => level >= min,
+                _ => true,
+            };
+            if passes_filter {
+                self.visible.push_back(number);
+            }
+            let Some(regex) = self.regex.as_ref() else {
+                continue;
+            };
+            for found in regex.find_iter(&text) {
+                if found.range().is_empty() {
+                    continue;
+                }
+                let start = text[..found.start()].chars().count();
+                let len = found.as_str().chars().count();
+                self.matches.push_back((number, start..start + len));
+            }
+        }
+        self.scanned = end;
+        if self
+            .current_match
+            .is_some_and(|current| current >= self.matches.len())
+        {
+            self.current_match = None;
+        }
+    }
+
+    fn jump_to_match(&mut self, forward: bool) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let len = self.matches.len();
+        let next = match (self.current_match, forward) {
+            (None, true) => 0,
+            (None, false) => len - 1,
+            (Some(current), true) => (current + 1) % len,
+            (Some(current), false) => (current + len - 1) % len,
+        };
+        self.current_match = Some(next);
+        self.scroll_to_line = Some(self.matches[next].0);
+    }
+
+    /// Lines that passed the filter since the user scrolled away from the bottom.
+    fn unread(&self) -> usize {
+        self.visible.len() - self.visible.partition_point(|line| *line < self.seen_end)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.held.is_some()
+    }
+
+    /// Feeds `output` to `terminal`, or holds on to it while paused.
+    pub fn receive(&mut self, terminal: &mut vt::Terminal, output: &OutputReceiver) {
+        match self.held.as_mut() {
+            Some(held) => {
+                for chunk in output.try_iter() {
+                    held.push(chunk, terminal.scrollback());
+                }
+            }
+            None => {
+                for chunk in output.try_iter() {
+                    terminal.advance(&chunk);
+                }
+            }
+        }
+    }
+
+    fn resume(&mut self, terminal: &mut vt::Terminal) {
+        let Some(held) = self.held.take() else { return };
+        if held.dropped > 0 {
+            terminal.advance(format!("\r\n[{} lines dropped while paused]\r\n", held.dropped).as_bytes());
+        }
+        for chunk in held.chunks {
+            terminal.advance(&chunk);
+        }
+    }
+
+    /// The `file:line:column` clicked since the last call, if any. Where it
+    /// should open is up to the caller, the paths are as the process printed them.
+    pub fn clicked_location(&mut self) -> Option<Location> {
+        self.clicked_location.take()
+    }
+
+    fn selected_text(&self, terminal: &vt::Terminal) -> String {
+        let Some((start, end)) = self.selection else {
+            return String::new();
+        };
+        let first = terminal.first_line_number();
+        let mut text = String::new();
+        for number in start.min(end).max(first)..=start.max(end) {
+            if let Some(line) = terminal.lines().get(number - first) {
+                text.push_str(&vt::line_text(line));
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    fn toolbar(&mut self, terminal: &mut vt::Terminal, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let search = ui.add(
+                TextEdit::singleline(&mut self.search)
+                    .hint_text("search")
+                    .desired_width(200.0),
+            );
+            let mut search_changed = search.changed();
+            search_changed |= ui
+                .toggle_value(&mut self.use_regex, ".*")
+                .on_hover_text("regex")
+                .changed();
+            search_changed |= ui
+                .toggle_value(&mut self.case_sensitive, "Aa")
+                .on_hover_text("case sensitive")
+                .changed();
+            if search_changed {
+                self.compile_search();
+                self.current_match = None;
+                self.needs_rescan = true;
+            }
+            let enter = search.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            if ui.button("<").on_hover_text("previous match").clicked() {
+                self.jump_to_match(false);
+            }
+            if ui.button(">").on_hover_text("next match").clicked() || enter {
+                self.jump_to_match(true);
+            }
+            if let Some(err) = &self.search_error {
+                ui.colored_label(ui.visuals().error_fg_color, "invalid regex")
+                    .on_hover_text(err);
+            } else if !self.search.is_empty() {
+                match self.current_match {
+                    Some(current) => ui.label(format!("{}/{}", current + 1, self.matches.len())),
+                    None => ui.label(format!("{} matches", self.matches.len())),
+                };
+            }
+
+            ui.separator();
+            let selected = self.min_level.map_or("all levels", Level::name);
+            egui::ComboBox::from_id_source("terminal level filter")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    let mut changed = ui.selectable_value(&mut self.min_level, None, "all levels").changed();
+                    for level in Level::ALL {
+                        changed |= ui
+                            .selectable_value(&mut self.min_level, Some(level), level.name())
+                            .changed();
+                    }
+                    if changed {
+                        self.needs_rescan = true;
+                    }
+                });
+
+            ui.separator();
+            if ui
+                .add_enabled(self.selection.is_some(), egui::Button::new("Copy"))
+                .on_hover_text("copy selected lines (shift+click to extend the selection)")
+                .clicked()
+            {
+                let text = self.selected_text(terminal);
+                ui.output_mut(|output| output.copied_text = text);
+            }
+            if ui.button("Copy all").clicked() {
+                let text = terminal.text();
+                ui.output_mut(|output| output.copied_text = text);
+            }
+
+            ui.separator();
+            ui.add(TextEdit::singleline(&mut self.save_path).desired_width(120.0));
+            if ui.button("Save log").clicked() {
+                self.save_status = Some(match std::fs::write(&self.save_path, terminal.text()) {
+                    Ok(()) => format!("saved to {}", self.save_path),
+                    Err(err) => format!("couldn't save: {err}"),
+                });
+            }
+            if let Some(status) = &self.save_status {
+                ui.label(status);
+            }
+
+            ui.separator();
+            match &self.held {
+                Some(held) => {
+                    let bytes: usize = held.chunks.iter().map(Vec::len).sum();
+                    let mut held_back = format!("{bytes} bytes of output held back");
+                    if held.dropped > 0 {
+                        held_back += &format!(", {} older lines dropped", held.dropped);
+                    }
+                    if ui
+                        .button("Resume")
+                        .on_hover_text(held_back)
+                        .clicked()
+                    {
+                        self.resume(terminal);
+                    }
+                }
+                None => {
+                    if ui
+                        .button("Pause")
+                        .on_hover_text("hold new output back until resumed")
+                        .clicked()
+                    {
+                        self.held = Some(Held::default());
+                    }
+                }
+            }
+
+            ui.separator();
+            let mut scrollback = terminal.scrollback();
+            ui.label("scrollback");
+            if ui
+                .add(DragValue::new(&mut scrollback).clamp_range(100..=1_000_000))
+                .changed()
+            {
+                terminal.set_scrollback(scrollback);
+            }
+        });
+        let copy_is_ours = self.focused || (self.hovered && !ui.ctx().wants_keyboard_input());
+        if self.selection.is_some()
+            && copy_is_ours
+            && ui.input(|input| input.events.iter().any(|event| matches!(event, egui::Event::Copy)))
+        {
+            let text = self.selected_text(terminal);
+            ui.output_mut(|output| output.copied_text = text);
+        }
+    }
+
+    /// Returns whatever was typed into the input line, it's already echoed to `terminal`.
+    pub fn show(&mut self, terminal: &mut vt::Terminal, ui: &mut Ui) -> Option<String> {
+        self.toolbar(terminal, ui);
+        let mut submitted = None;
+        ui.with_layout(Layout::bottom_up(Align::LEFT), |ui| {
+            submitted = self.input.show(ui);
+            if let Some(line) = &submitted {
+                terminal.advance(format!("{line}\n").as_bytes());
+            }
+            if !self.at_bottom {
+                let unread = self.unread();
+                let text = match unread {
+                    0 => "Jump to latest".to_string(),
+                    unread => format!("Jump to latest ({unread} new)"),
+                };
+                if ui.button(text).clicked() {
+                    self.jump_to_bottom = true;
+                }
+            }
+            ui.with_layout(Layout::top_down(Align::LEFT), |ui| self.rows(terminal, ui));
+        });
+        submitted
+    }
+
+    /// Only lays out the rows that are actually on screen, so the cost of a frame
+    /// doesn't grow with the length of the log.
+    pub fn rows(&mut self, terminal: &vt::Terminal, ui: &mut Ui) {
+        self.sync(terminal);
+        let row_height = ui.fonts(|fonts| fonts.row_height(&terminal_font()));
+        let row_height_with_spacing = row_height + ui.spacing().item_spacing.y;
+        let mut scroll_area = ScrollArea::new(true).auto_shrink(false).stick_to_bottom(true);
+        if std::mem::take(&mut self.jump_to_bottom) {
+            // landing exactly on the end makes the scroll area stick to it again
+            let offset = self.visible.len() as f32 * row_height_with_spacing - ui.available_height();
+            scroll_area = scroll_area.vertical_scroll_offset(offset.max(0.0));
+        } else if let Some(number) = self.scroll_to_line.take() {
+            let row = self.visible.partition_point(|line| *line < number);
+            let offset = row as f32 * row_height_with_spacing - ui.available_height() / 2.0;
+            scroll_area = scroll_area.vertical_scroll_offset(offset.max(0.0));
+        }
+        let first = terminal.first_line_number();
+        let lines = terminal.lines();
+        let current = self.current_match.map(|current| &self.matches[current]);
+        let shift = ui.input(|input| input.modifiers.shift);
+        let char_width = ui.fonts(|fonts| fonts.glyph_width(&terminal_font(), 'M'));
+        let mut clicked = None;
+        let mut clicked_location = None;
+        let output = scroll_area.show_rows(ui, row_height, self.visible.len(), |ui, rows| {
+            for row in rows {
+                let number = self.visible[row];
+                let start = self.matches.partition_point(|(line, _)| *line < number);
+                let end = self.matches.partition_point(|(line, _)| *line <= number);
+                let highlights = self
+                    .matches
+                    .range(start..end)
+                    .map(|(_, range)| range.clone())
+                    .collect::<Vec<_>>();
+                let current = current
+                    .filter(|(line, _)| *line == number)
+                    .map(|(_, range)| range);
+                let line = &lines[number - first];
+                let links = Location::find_all(&vt::line_text(line));
+                let ranges = links.iter().map(|(range, _)| range.clone()).collect::<Vec<_>>();
+                let mut job = highlighted_line_layout_job(line, ui, &highlights, current, &ranges);
+                let selected = self.selection.is_some_and(|(start, end)| {
+                    (start.min(end)..=start.max(end)).contains(&number)
+                });
+                if selected {
+                    for section in job.sections.iter_mut() {
+                        if section.format.background == Color32::TRANSPARENT {
+                            section.format.background = ui.visuals().selection.bg_fill;
+                        }
+                    }
+                }
+                let response = ui.add(Label::new(job).wrap(false).sense(Sense::click()));
+                // the terminal font is monospace, so the pointer's column is just a division
+                let link = response.hover_pos().and_then(|pos| {
+                    let col = ((pos.x - response.rect.min.x) / char_width) as usize;
+                    links.iter().find(|(range, _)| range.contains(&col))
+                });
+                match link {
+                    Some((_, location)) => {
+                        let response = response
+                            .on_hover_cursor(CursorIcon::PointingHand)
+                            .on_hover_text(format!("open {location}"));
+                        if response.clicked() {
+                            clicked_location = Some(location.clone());
+                        }
+                    }
+                    None if response.clicked() => clicked = Some(number),
+                    None => {}
+                }
+            }
+        });
+        self.hovered = ui.rect_contains_pointer(output.inner_rect);
+        if clicked.is_some() {
+            self.focused = true;
+        } else if ui.input(|input| input.pointer.any_pressed()) && !self.hovered {
+            self.focused = false;
+        }
+        let bottom = output.state.offset.y + output.inner_rect.height();
+        self.at_bottom = bottom >= output.content_size.y - row_height;
+        if self.at_bottom {
+            self.seen_end = terminal.first_line_number() + lines.len();
+        }
+        if clicked_location.is_some() {
+            self.clicked_location = clicked_location;
+        }
+        if let Some(number) = clicked {
+            self.selection = match self.selection {
+                Some((start, _)) if shift => Some((start, number)),
+                Some((start, end)) if start == number && end == number => None,
+                _ => Some((number, number)),
+            };
+        }
+    }
+}
+
+/// Feeds whatever output arrived since the last frame into `terminal` (unless
+/// the view is paused) and shows it. Returns the line the user typed for the
+/// process' stdin, if any.
+pub fn display_terminal(
+    view: &mut TerminalView,
+    terminal: &mut vt::Terminal,
+    output: &OutputReceiver,
+    ui: &mut Ui,
+) -> Option<String> {
+    view.receive(terminal, output);
+    view.show(terminal, ui)
+}
@@ -0,0 +1,333 @@
+//! Picks the interesting parts out of a failed run's output: rustc's errors
+//! and the panic that took the game down.
+
+use crate::vt;
+use regex::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// `src/lib.rs:42:5`, as rustc and the panic handler print them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+impl Location {
+    /// Parses `file:line[:column]`, trailing punctuation is ignored.
+    pub fn parse(text: &str) -> Option<Location> {
+        let text = text.trim().trim_end_matches([':', ',', ')']);
+        let (rest, last) = text.rsplit_once(':')?;
+        let last = last.parse().ok()?;
+        if let Some((file, line)) = rest.rsplit_once(':') {
+            if let (false, Ok(line)) = (file.is_empty(), line.parse()) {
+                return Some(Location {
+                    file: file.to_string(),
+                    line,
+                    column: Some(last),
+                });
+            }
+        }
+        if rest.is_empty() {
+            return None;
+        }
+        Some(Location {
+            file: rest.to_string(),
+            line: last,
+            column: None,
+        })
+    }
+
+    /// Every `file.ext:line[:column]` in a line of output, with the chars it
+    /// spans. The file needs an extension so timestamps and ports don't count.
+    pub fn find_all(line: &str) -> Vec<(Range<usize>, Location)> {
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PATTERN.get_or_init(|| {
+            Regex::new(r"[^\s:'\x22`()\[\]<>]*\.[A-Za-z]\w*:\d+(?::\d+)?").unwrap()
+        });
+        pattern
+            .find_iter(line)
+            .filter_map(|found| {
+                let location = Location::parse(found.as_str())?;
+                let start = line[..found.start()].chars().count();
+                let len = found.as_str().chars().count();
+                Some((start..start + len, location))
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{column}")?;
+        }
+        Ok(())
+    }
+}
+
+/// One `error[E0425]: ...` block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub code: Option<String>,
+    pub message: String,
+    pub location: Option<Location>,
+    /// The rest of the block: the source snippet, notes and help.
+    pub details: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panic {
+    pub thread: String,
+    pub message: String,
+    pub location: Option<Location>,
+    /// Empty unless the game ran with `RUST_BACKTRACE` set.
+    pub backtrace: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Failure {
+    pub errors: Vec<CompileError>,
+    pub panic: Option<Panic>,
+}
+
+impl Failure {
+    /// `output` is the raw output of the run, escape codes and all.
+    pub fn parse(output: &[u8]) -> Failure {
+        // run it through a terminal so cargo's progress bar and colors don't get in the way
+        let mut terminal = vt::Terminal::new(1000, vt::DEFAULT_ROWS, 100_000);
+        terminal.advance(output);
+        let lines = terminal
+            .lines()
+            .iter()
+            .map(vt::line_text)
+            .collect::<Vec<_>>();
+        Failure::parse_lines(&lines)
+    }
+
+    fn parse_lines(lines: &[String]) -> Failure {
+        let mut failure = Failure::default();
+        let mut lines = lines.iter().peekable();
+        while let Some(line) = lines.next() {
+            if let Some(mut error) = parse_error_header(line) {
+                while let Some(detail) = lines.next_if(|line| !line.trim().is_empty()) {
+                    if error.location.is_none() {
+                        error.location = detail
+                            .trim_start()
+                            .strip_prefix("--> ")
+                            .and_then(Location::parse);
+                    }
+                    error.details.push(detail.clone());
+                }
+                failure.errors.push(error);
+            } else if let Some(mut panic) = parse_panic_header(line) {
+                // since rust 1.73 the message goes on the lines after the header
+                if panic.message.is_empty() {
+                    let mut message = Vec::new();
+                    while let Some(line) = lines.next_if(|line| {
+                        !line.is_empty()
+                            && !line.starts_with("note:")
+                            && !line.starts_with("stack backtrace:")
+                    }) {
+                        message.push(line.as_str());
+                    }
+                    panic.message = message.join("\n");
+                }
+                failure.panic = Some(panic);
+            } else if line.starts_with("stack backtrace:") {
+                let Some(panic) = failure.panic.as_mut() else {
+                    continue;
+                };
+                while let Some(frame) = lines.next_if(|line| line.starts_with(' ')) {
+                    panic.backtrace.push(frame.trim().to_string());
+                }
+            }
+        }
+        failure
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty() && self.panic.is_none()
+    }
+}
+
+fn parse_error_header(line: &str) -> Option<CompileError> {
+    let rest = line.strip_prefix("error")?;
+    let (code, message) = match rest.strip_prefix('[') {
+        Some(rest) => {
+            let (code, message) = rest.split_once("]:")?;
+            (Some(code.to_string()), message)
+        }
+        None => (None, rest.strip_prefix(':')?),
+    };
+    let message = message.trim();
+    // cargo's summary of the errors we already have
+    if message.starts_with("could not compile") || message.starts_with("aborting due to") {
+        return None;
+    }
+    Some(CompileError {
+        code,
+        message: message.to_string(),
+        location: None,
+        details: Vec::new(),
+    })
+}
+
+/// `thread 'main' panicked at src/lib.rs:1:2:` or, before rust 1.73,
+/// `thread 'main' panicked at 'message', src/lib.rs:1:2`.
+fn parse_panic_header(line: &str) -> Option<Panic> {
+    let rest = line.strip_prefix("thread '")?;
+    let (thread, rest) = rest.split_once('\'')?;
+    let rest = rest.trim_start().strip_prefix("panicked at ")?;
+    let (message, location) = match rest.strip_prefix('\'') {
+        Some(rest) => {
+            let (message, location) = rest.rsplit_once("', ")?;
+            (message.to_string(), Location::parse(location))
+        }
+        None => (String::new(), Location::parse(rest)),
+    };
+    Some(Panic {
+        thread: thread.to_string(),
+        message,
+        location,
+        backtrace: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(file: &str, line: usize, column: Option<usize>) -> Location {
+        Location {
+            file: file.to_string(),
+            line,
+            column,
+        }
+    }
+
+    #[test]
+    fn parse_location() {
+        let cases = [
+            ("src/lib.rs:42:5", Some(location("src/lib.rs", 42, Some(5)))),
+            ("src/main.rs:7", Some(location("src/main.rs", 7, None))),
+            ("src/lib.rs:3:9:", Some(location("src/lib.rs", 3, Some(9)))),
+            ("(src/lib.rs:3),", Some(location("(src/lib.rs", 3, None))),
+            (
+                r"C:\game\src\lib.rs:1:2",
+                Some(location(r"C:\game\src\lib.rs", 1, Some(2))),
+            ),
+            (":12", None),
+            ("src/lib.rs", None),
+            ("src/lib.rs:x", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(Location::parse(text), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn find_locations_in_a_line() {
+        let cases = [
+            (
+                "  --> src/lib.rs:12:5",
+                vec![(6..21, location("src/lib.rs", 12, Some(5)))],
+            ),
+            (
+                "thread 'main' panicked at src/lib.rs:10:9:",
+                vec![(26..41, location("src/lib.rs", 10, Some(9)))],
+            ),
+            (
+                "→ src/a.rs:1 and b.rs:2:3",
+                vec![
+                    (2..12, location("src/a.rs", 1, None)),
+                    (17..25, location("b.rs", 2, Some(3))),
+                ],
+            ),
+            ("at 12:30:45 on localhost:8080", vec![]),
+            ("   Compiling game v0.1.0 (/home/me/game)", vec![]),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(Location::find_all(line), expected, "{line}");
+        }
+    }
+
+    const COMPILE_ERRORS: &str = "   Compiling game v0.1.0 (/home/me/game)
+\x1b[1m\x1b[91merror[E0425]\x1b[0m\x1b[1m: cannot find value `x` in this scope\x1b[0m
+ --> src/lib.rs:3:13
+  |
+3 |     let y = x;
+  |             ^ not found in this scope
+
+error: expected `;`, found `}`
+  --> src/main.rs:1:2
+   |
+1  | }
+   |  ^
+
+warning: unused variable: `y`
+error: could not compile `game` (lib) due to 2 previous errors
+";
+
+    #[test]
+    fn multi_line_compile_errors() {
+        let failure = Failure::parse(COMPILE_ERRORS.as_bytes());
+        assert!(failure.panic.is_none());
+        let [first, second] = &failure.errors[..] else {
+            panic!("expected two errors, got {:?}", failure.errors);
+        };
+        assert_eq!(first.code.as_deref(), Some("E0425"));
+        assert_eq!(first.message, "cannot find value `x` in this scope");
+        assert_eq!(first.location, Some(location("src/lib.rs", 3, Some(13))));
+        assert_eq!(first.details.len(), 4);
+        assert_eq!(first.details[2], "3 |     let y = x;");
+        assert_eq!(second.code, None);
+        assert_eq!(second.message, "expected `;`, found `}`");
+        assert_eq!(second.location, Some(location("src/main.rs", 1, Some(2))));
+    }
+
+    #[test]
+    fn panics() {
+        let cases = [
+            (
+                "thread 'main' panicked at src/lib.rs:10:5:\nindex out of bounds\nsecond line\nnote: run with `RUST_BACKTRACE=1`\n",
+                "main",
+                "index out of bounds\nsecond line",
+            ),
+            (
+                "thread 'Compute Task Pool (0)' panicked at 'boom', src/lib.rs:10:5\n",
+                "Compute Task Pool (0)",
+                "boom",
+            ),
+        ];
+        for (output, thread, message) in cases {
+            let panic = Failure::parse(output.as_bytes()).panic.expect(output);
+            assert_eq!(panic.thread, thread);
+            assert_eq!(panic.message, message);
+            assert_eq!(panic.location, Some(location("src/lib.rs", 10, Some(5))));
+            assert!(panic.backtrace.is_empty());
+        }
+    }
+
+    #[test]
+    fn panic_with_backtrace() {
+        let output = "thread 'main' panicked at src/lib.rs:1:1:\nboom\nstack backtrace:\n   0: rust_begin_unwind\n   1: mylib::setup\n             at ./src/lib.rs:1:1\n";
+        let panic = Failure::parse(output.as_bytes()).panic.unwrap();
+        assert_eq!(
+            panic.backtrace,
+            [
+                "0: rust_begin_unwind",
+                "1: mylib::setup",
+                "at ./src/lib.rs:1:1"
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_that_arent_failures() {
+        let output = "   Compiling game v0.1.0\nwarning: unused import\nerror: could not compile `game`\nerror: aborting due to 1 previous error\nstack backtrace:\n   0: nothing panicked\nthread 'main' did something\n";
+        assert!(Failure::parse(output.as_bytes()).is_empty());
+    }
+}
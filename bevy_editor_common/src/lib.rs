@@ -0,0 +1,31 @@
+//! Everything the project manager, the hot reload watcher and the project
+//! template share: spawning processes and streaming their output, the terminal
+//! model, the watcher <-> editor protocol, following builds and making sense
+//! of failed runs, the language server client (`lsp` feature), the project's
+//! editor config (`config` feature) and the egui terminal widgets.
+//!
+//! The widgets are compiled once per egui version, pick the one you're on with
+//! the `egui_27` / `egui_26` features.
+
+pub mod build;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod failure;
+pub mod log;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod process;
+pub mod protocol;
+pub mod vt;
+
+#[cfg(feature = "egui_27")]
+pub mod egui_27 {
+    use egui_0_27 as egui;
+    include!("egui_view.rs");
+}
+
+#[cfg(feature = "egui_26")]
+pub mod egui_26 {
+    use egui_0_26 as egui;
+    include!("egui_view.rs");
+}
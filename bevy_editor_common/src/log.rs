@@ -0,0 +1,85 @@
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub const ALL: [Level; 5] = [
+        Level::Trace,
+        Level::Debug,
+        Level::Info,
+        Level::Warn,
+        Level::Error,
+    ];
+
+    /// Recognizes bevy's `tracing` prefix (`2024-05-01T12:00:00Z  INFO bevy_render: ...`)
+    /// as well as cargo's `warning:` / `error[E0425]:` lines.
+    pub fn detect(text: &str) -> Option<Level> {
+        text.split_whitespace().take(3).find_map(|word| match word {
+            "TRACE" => Some(Level::Trace),
+            "DEBUG" => Some(Level::Debug),
+            "INFO" => Some(Level::Info),
+            "WARN" | "warning:" => Some(Level::Warn),
+            "ERROR" | "error:" => Some(Level::Error),
+            word if word.starts_with("error[") => Some(Level::Error),
+            _ => None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vt;
+
+    /// The level of a line of output, read the way the log view does: through
+    /// a terminal, so colors are gone by the time `detect` sees it.
+    fn detect(output: &str) -> Option<Level> {
+        let mut terminal = vt::Terminal::new(200, vt::DEFAULT_ROWS, 100);
+        terminal.advance(output.as_bytes());
+        Level::detect(&vt::line_text(&terminal.lines()[0]))
+    }
+
+    #[test]
+    fn detects_levels() {
+        let cases = [
+            (
+                "\x1b[2m2024-05-01T12:00:00.000Z\x1b[0m \x1b[32m INFO\x1b[0m \x1b[2mbevy_render\x1b[0m\x1b[2m:\x1b[0m AdapterInfo",
+                Some(Level::Info),
+            ),
+            ("\x1b[2m2024-05-01T12:00:00Z\x1b[0m \x1b[33m WARN\x1b[0m game: low fps", Some(Level::Warn)),
+            ("2024-05-01T12:00:00Z TRACE wgpu: poll", Some(Level::Trace)),
+            ("2024-05-01T12:00:00Z DEBUG game: tick", Some(Level::Debug)),
+            ("2024-05-01T12:00:00Z  WARN game: low fps", Some(Level::Warn)),
+            ("2024-05-01T12:00:00Z ERROR game: no camera", Some(Level::Error)),
+            ("WARN plain", Some(Level::Warn)),
+            ("ERROR plain", Some(Level::Error)),
+            ("warning: unused variable: `x`", Some(Level::Warn)),
+            ("\x1b[1m\x1b[91merror[E0425]\x1b[0m\x1b[1m: cannot find value\x1b[0m", Some(Level::Error)),
+            ("error: could not compile `game`", Some(Level::Error)),
+            ("   Compiling game v0.1.0", None),
+            ("hello from the game", None),
+            ("", None),
+            // only the prefix counts, not a level further into the message
+            ("the player said it would ERROR out", None),
+            ("INFORMATION", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(detect(line), expected, "{line:?}");
+        }
+    }
+}
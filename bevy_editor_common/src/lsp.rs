@@ -0,0 +1,616 @@
+//! A small Language Server Protocol client, enough for rust-analyzer to give
+//! the code editor diagnostics, hovers, go-to-definition and completion.
+//!
+//! Nothing here blocks: messages are read and written on background threads
+//! and [`LspClient::poll`] hands over whatever arrived since the last frame. Any
+//! server speaking JSON-RPC over stdio works, which is how `tests/lsp.rs` tests
+//! it against the `lsp_stub` binary instead of a real rust-analyzer.
+
+use crate::protocol::MAX_PAYLOAD;
+use crossbeam_channel::{Receiver, Sender};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// Zero based line and UTF-16 column, as the protocol counts them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+impl Position {
+    /// The position of the char at `char_index` in `text`.
+    pub fn from_char_index(text: &str, char_index: usize) -> Position {
+        let mut position = Position::default();
+        for c in text.chars().take(char_index) {
+            if c == '\n' {
+                position.line += 1;
+                position.character = 0;
+            } else {
+                position.character += c.len_utf16() as u32;
+            }
+        }
+        position
+    }
+
+    /// The char index of this position in `text`, clamped to the end of its line.
+    pub fn to_char_index(self, text: &str) -> usize {
+        let mut line = 0;
+        let mut character = 0;
+        for (index, c) in text.chars().enumerate() {
+            if line == self.line && (character >= self.character || c == '\n') {
+                return index;
+            }
+            if c == '\n' {
+                line += 1;
+                character = 0;
+            } else if line == self.line {
+                character += c.len_utf16() as u32;
+            }
+        }
+        text.chars().count()
+    }
+
+    fn from_json(value: &Value) -> Option<Position> {
+        Some(Position {
+            line: value.get("line")?.as_u64()? as u32,
+            character: value.get("character")?.as_u64()? as u32,
+        })
+    }
+
+    fn to_json(self) -> Value {
+        json!({ "line": self.line, "character": self.character })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Range {
+    fn from_json(value: &Value) -> Option<Range> {
+        Some(Range {
+            start: Position::from_json(value.get("start")?)?,
+            end: Position::from_json(value.get("end")?)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Severity,
+    pub message: String,
+    pub source: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub range: Range,
+}
+
+impl Location {
+    fn from_json(value: &Value) -> Option<Location> {
+        // `Location` or `LocationLink`
+        let uri = value.get("uri").or_else(|| value.get("targetUri"))?;
+        let range = value
+            .get("targetSelectionRange")
+            .or_else(|| value.get("range"))?;
+        Some(Location {
+            path: uri_to_path(uri.as_str()?)?,
+            range: Range::from_json(range)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub detail: Option<String>,
+    pub insert_text: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LspEvent {
+    /// New diagnostics for this file, see [`LspClient::diagnostics`].
+    Diagnostics(PathBuf),
+    Hover {
+        path: PathBuf,
+        position: Position,
+        /// `None` when there's nothing to say about that spot.
+        text: Option<String>,
+    },
+    Definition {
+        path: PathBuf,
+        position: Position,
+        locations: Vec<Location>,
+    },
+    Completion {
+        path: PathBuf,
+        position: Position,
+        items: Vec<CompletionItem>,
+    },
+    /// The server answered a request with an error, or sent something we
+    /// couldn't make sense of.
+    Error(String),
+    Exited,
+}
+
+enum Pending {
+    Initialize,
+    Hover(PathBuf, Position),
+    Definition(PathBuf, Position),
+    Completion(PathBuf, Position),
+    Shutdown,
+}
+
+pub struct LspClient {
+    child: Child,
+    /// To the writer thread, a server that stops reading (rust-analyzer while
+    /// it indexes) mustn't block the frame. Only taken when dropping.
+    outgoing: Option<Sender<Value>>,
+    /// Disconnects once the writer thread is done.
+    writer_done: Receiver<()>,
+    incoming: Receiver<Value>,
+    next_id: u64,
+    pending: HashMap<u64, Pending>,
+    initialized: bool,
+    /// Everything we tried to send before the server was initialized.
+    queued: Vec<Value>,
+    versions: HashMap<PathBuf, i32>,
+    diagnostics: HashMap<PathBuf, Vec<Diagnostic>>,
+    exited: bool,
+}
+
+impl LspClient {
+    pub fn rust_analyzer(root: &Path) -> io::Result<LspClient> {
+        LspClient::spawn(Command::new("rust-analyzer"), root)
+    }
+
+    /// Starts `command` as a language server for the project in `root`.
+    pub fn spawn(mut command: Command, root: &Path) -> io::Result<LspClient> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (tx, incoming) = crossbeam_channel::unbounded();
+        thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            while let Ok(message) = read_message(&mut stdout) {
+                if tx.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+        let (outgoing, to_write) = crossbeam_channel::unbounded();
+        let (done, writer_done) = crossbeam_channel::bounded(0);
+        thread::spawn(move || write_messages(stdin, to_write, done));
+        let mut client = LspClient {
+            child,
+            outgoing: Some(outgoing),
+            writer_done,
+            incoming,
+            next_id: 0,
+            pending: HashMap::new(),
+            initialized: false,
+            queued: Vec::new(),
+            versions: HashMap::new(),
+            diagnostics: HashMap::new(),
+            exited: false,
+        };
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": path_to_uri(root),
+            "workspaceFolders": [{ "uri": path_to_uri(root), "name": "project" }],
+            "capabilities": {
+                "general": { "positionEncodings": ["utf-16"] },
+                "textDocument": {
+                    "synchronization": { "didSave": true },
+                    "publishDiagnostics": {},
+                    "hover": { "contentFormat": ["plaintext", "markdown"] },
+                    "definition": { "linkSupport": true },
+                    "completion": { "completionItem": { "snippetSupport": false } },
+                },
+            },
+        });
+        let id = client.next_id();
+        client.pending.insert(id, Pending::Initialize);
+        client.write(json!({ "jsonrpc": "2.0", "id": id, "method": "initialize", "params": params }));
+        Ok(client)
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// A server that went away shows up as [`LspEvent::Exited`], so what
+    /// couldn't be written is dropped.
+    fn write(&self, message: Value) {
+        if let Some(outgoing) = &self.outgoing {
+            outgoing.send(message).ok();
+        }
+    }
+
+    /// Holds messages back until the server is initialized.
+    fn send(&mut self, message: Value) {
+        if !self.initialized {
+            self.queued.push(message);
+            return;
+        }
+        self.write(message);
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn request(&mut self, method: &str, params: Value, pending: Pending) {
+        let id = self.next_id();
+        self.pending.insert(id, pending);
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+    }
+
+    pub fn is_open(&self, path: &Path) -> bool {
+        self.versions.contains_key(path)
+    }
+
+    pub fn did_open(&mut self, path: &Path, language_id: &str, text: &str) {
+        self.versions.insert(path.to_path_buf(), 0);
+        self.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": {
+                "uri": path_to_uri(path),
+                "languageId": language_id,
+                "version": 0,
+                "text": text,
+            }}),
+        );
+    }
+
+    /// Sends the whole text, buffers are small enough that diffing isn't worth it.
+    pub fn did_change(&mut self, path: &Path, text: &str) {
+        let Some(version) = self.versions.get_mut(path) else {
+            return;
+        };
+        *version += 1;
+        let version = *version;
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": path_to_uri(path), "version": version },
+                "contentChanges": [{ "text": text }],
+            }),
+        );
+    }
+
+    pub fn did_save(&mut self, path: &Path) {
+        if !self.is_open(path) {
+            return;
+        }
+        self.notify(
+            "textDocument/didSave",
+            json!({ "textDocument": { "uri": path_to_uri(path) } }),
+        );
+    }
+
+    pub fn did_close(&mut self, path: &Path) {
+        if self.versions.remove(path).is_none() {
+            return;
+        }
+        self.diagnostics.remove(path);
+        self.notify(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": path_to_uri(path) } }),
+        );
+    }
+
+    fn position_params(path: &Path, position: Position) -> Value {
+        json!({
+            "textDocument": { "uri": path_to_uri(path) },
+            "position": position.to_json(),
+        })
+    }
+
+    pub fn hover(&mut self, path: &Path, position: Position) {
+        let params = Self::position_params(path, position);
+        self.request("textDocument/hover", params, Pending::Hover(path.to_path_buf(), position));
+    }
+
+    pub fn definition(&mut self, path: &Path, position: Position) {
+        let params = Self::position_params(path, position);
+        let pending = Pending::Definition(path.to_path_buf(), position);
+        self.request("textDocument/definition", params, pending);
+    }
+
+    pub fn completion(&mut self, path: &Path, position: Position) {
+        let params = Self::position_params(path, position);
+        let pending = Pending::Completion(path.to_path_buf(), position);
+        self.request("textDocument/completion", params, pending);
+    }
+
+    pub fn diagnostics(&self, path: &Path) -> &[Diagnostic] {
+        self.diagnostics.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every file with diagnostics, sorted by path.
+    pub fn all_diagnostics(&self) -> Vec<(&Path, &[Diagnostic])> {
+        let mut all = self
+            .diagnostics
+            .iter()
+            .filter(|(_, diagnostics)| !diagnostics.is_empty())
+            .map(|(path, diagnostics)| (path.as_path(), diagnostics.as_slice()))
+            .collect::<Vec<_>>();
+        all.sort_by_key(|(path, _)| *path);
+        all
+    }
+
+    /// Handles everything the server sent since the last call.
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let mut events = Vec::new();
+        let messages = self.incoming.try_iter().collect::<Vec<_>>();
+        for message in messages {
+            self.handle(message, &mut events);
+        }
+        if !self.exited && matches!(self.child.try_wait(), Ok(Some(_))) {
+            self.exited = true;
+            events.push(LspEvent::Exited);
+        }
+        events
+    }
+
+    fn handle(&mut self, message: Value, events: &mut Vec<LspEvent>) {
+        let method = message.get("method").and_then(Value::as_str);
+        match (message.get("id"), method) {
+            // a request from the server, we don't support any but it's waiting for an answer
+            (Some(id), Some(method)) => {
+                let result = match method {
+                    "workspace/configuration" => {
+                        let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                        Value::Array(vec![Value::Null; items])
+                    }
+                    _ => Value::Null,
+                };
+                let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                self.write(response);
+            }
+            (None, Some("textDocument/publishDiagnostics")) => {
+                let params = &message["params"];
+                let Some(path) = params["uri"].as_str().and_then(uri_to_path) else {
+                    return;
+                };
+                let diagnostics = params["diagnostics"]
+                    .as_array()
+                    .map(|diagnostics| diagnostics.iter().filter_map(parse_diagnostic).collect())
+                    .unwrap_or_default();
+                self.diagnostics.insert(path.clone(), diagnostics);
+                events.push(LspEvent::Diagnostics(path));
+            }
+            (None, Some(_)) => {}
+            (Some(id), None) => {
+                let Some(pending) = id.as_u64().and_then(|id| self.pending.remove(&id)) else {
+                    return;
+                };
+                if let Some(error) = message.get("error") {
+                    let text = error["message"].as_str().unwrap_or("unknown error");
+                    events.push(LspEvent::Error(text.to_string()));
+                    return;
+                }
+                self.handle_response(pending, &message["result"], events);
+            }
+            (None, None) => events.push(LspEvent::Error(format!("unexpected message {message}"))),
+        }
+    }
+
+    fn handle_response(&mut self, pending: Pending, result: &Value, events: &mut Vec<LspEvent>) {
+        match pending {
+            Pending::Initialize => {
+                self.initialized = true;
+                self.write(json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }));
+                for message in std::mem::take(&mut self.queued) {
+                    self.write(message);
+                }
+            }
+            Pending::Hover(path, position) => {
+                let text = hover_text(&result["contents"]).filter(|text| !text.trim().is_empty());
+                events.push(LspEvent::Hover { path, position, text });
+            }
+            Pending::Definition(path, position) => {
+                let locations = match result {
+                    Value::Array(locations) => locations.iter().filter_map(Location::from_json).collect(),
+                    location => Location::from_json(location).into_iter().collect(),
+                };
+                events.push(LspEvent::Definition { path, position, locations });
+            }
+            Pending::Completion(path, position) => {
+                // either a list of items or `{ isIncomplete, items }`
+                let items = result
+                    .as_array()
+                    .or_else(|| result["items"].as_array())
+                    .map(|items| items.iter().filter_map(parse_completion_item).collect())
+                    .unwrap_or_default();
+                events.push(LspEvent::Completion { path, position, items });
+            }
+            Pending::Shutdown => {}
+        }
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        if self.initialized {
+            let id = self.next_id();
+            self.pending.insert(id, Pending::Shutdown);
+            self.write(json!({ "jsonrpc": "2.0", "id": id, "method": "shutdown" }));
+            self.write(json!({ "jsonrpc": "2.0", "method": "exit" }));
+        }
+        // give the writer a moment to get those out, unless the server isn't reading
+        drop(self.outgoing.take());
+        self.writer_done.recv_timeout(Duration::from_millis(500)).ok();
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// Runs until the client is dropped or the server stops taking messages,
+/// `done` goes with it.
+fn write_messages(mut stdin: ChildStdin, to_write: Receiver<Value>, done: Sender<()>) {
+    for message in to_write {
+        let body = message.to_string();
+        let written = write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).and_then(|_| stdin.flush());
+        if written.is_err() {
+            break;
+        }
+    }
+    drop(done);
+}
+
+fn read_message(reader: &mut impl BufRead) -> io::Result<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no Content-Length"))?;
+    if length > MAX_PAYLOAD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("a {length} byte message is over the {MAX_PAYLOAD} byte limit"),
+        ));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn parse_diagnostic(value: &Value) -> Option<Diagnostic> {
+    let severity = match value.get("severity").and_then(Value::as_u64) {
+        Some(2) => Severity::Warning,
+        Some(3) => Severity::Information,
+        Some(4) => Severity::Hint,
+        _ => Severity::Error,
+    };
+    Some(Diagnostic {
+        range: Range::from_json(value.get("range")?)?,
+        severity,
+        message: value.get("message")?.as_str()?.to_string(),
+        source: value.get("source").and_then(Value::as_str).map(str::to_string),
+    })
+}
+
+fn parse_completion_item(value: &Value) -> Option<CompletionItem> {
+    let label = value.get("label")?.as_str()?.to_string();
+    let insert_text = value
+        .pointer("/textEdit/newText")
+        .or_else(|| value.get("insertText"))
+        .and_then(Value::as_str)
+        .unwrap_or(&label)
+        .to_string();
+    Some(CompletionItem {
+        detail: value.get("detail").and_then(Value::as_str).map(str::to_string),
+        label,
+        insert_text,
+    })
+}
+
+/// `MarkupContent`, a `MarkedString` or a list of them.
+fn hover_text(contents: &Value) -> Option<String> {
+    match contents {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(parts) => Some(
+            parts
+                .iter()
+                .filter_map(hover_text)
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        ),
+        Value::Object(_) => contents["value"].as_str().map(str::to_string),
+        _ => None,
+    }
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                uri.push(byte as char)
+            }
+            byte => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut input = encoded.bytes();
+    while let Some(byte) = input.next() {
+        if byte != b'%' {
+            bytes.push(byte);
+            continue;
+        }
+        let hex = [input.next()?, input.next()?];
+        bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // `/C:/project` on windows
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] => &path[1..],
+        _ => &path,
+    };
+    Some(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_message() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"result":null}"#;
+        let framed = format!("Content-Length: {}\r\nContent-Type: x\r\n\r\n{body}", body.len());
+        let message = read_message(&mut framed.as_bytes()).unwrap();
+        assert_eq!(message["id"], 1);
+    }
+
+    #[test]
+    fn oversized_lengths_are_refused_before_allocating() {
+        let framed = format!("Content-Length: {}\r\n\r\n", usize::MAX);
+        let err = read_message(&mut framed.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
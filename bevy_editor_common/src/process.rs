@@ -0,0 +1,64 @@
+use crate::vt;
+use crossbeam_channel::{Receiver, Sender};
+use std::io;
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+
+/// Output arrives in whatever chunks the pipe/socket hands us.
+pub type OutputReceiver = Receiver<Vec<u8>>;
+
+/// Spawns `command` with all three stdio streams piped, stdout and stderr both
+/// end up in the returned receiver. With `echo` they're also copied to our own
+/// stdout, which is how the watcher's output reaches the project manager.
+pub fn command_channels(mut command: Command, echo: bool) -> io::Result<(OutputReceiver, Child)> {
+    command.stderr(Stdio::piped());
+    command.stdout(Stdio::piped());
+    command.stdin(Stdio::piped());
+    // we're not a tty, but we render like one
+    command.env("CARGO_TERM_COLOR", "always");
+    command.env("CARGO_TERM_PROGRESS_WHEN", "always");
+    command.env("CARGO_TERM_PROGRESS_WIDTH", vt::DEFAULT_COLUMNS.to_string());
+    let mut child = command.spawn()?;
+    let (tx, rx) = crossbeam_channel::unbounded();
+    if let Some(stdout) = child.stdout.take() {
+        stream_output(stdout, tx.clone(), echo);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        stream_output(stderr, tx, echo);
+    }
+    Ok((rx, child))
+}
+
+/// Forwards everything read from `reader` into `tx` on a background thread,
+/// until either side is closed.
+pub fn stream_output(mut reader: impl Read + Send + 'static, tx: Sender<Vec<u8>>, echo: bool) {
+    thread::spawn(move || {
+        let mut buf = [0; 4096];
+        loop {
+            let read = match reader.read(&mut buf) {
+                Ok(0) => return,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => return,
+            };
+            if echo {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(&buf[..read]).ok();
+                stdout.flush().ok();
+            }
+            if tx.send(buf[..read].to_vec()).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+pub fn write_stdin(child: &mut Child, line: &str) {
+    let Some(stdin) = child.stdin.as_mut() else {
+        return;
+    };
+    if let Err(err) = writeln!(stdin, "{line}").and_then(|_| stdin.flush()) {
+        eprintln!("couldn't write to stdin: {err}");
+    }
+}
@@ -0,0 +1,251 @@
+use std::io;
+use std::io::{Read, Write};
+
+/// Env var with the port the watcher streams the game's output on.
+pub const RX_PORT: &str = "RX_PORT";
+/// Env var with the port the watcher listens for [`EditorToWatcher`] messages on.
+pub const TX_PORT: &str = "TX_PORT";
+/// Env var the watcher sets on an `ONLY_UI` run that follows a failed one, with
+/// the path of a [`FailureReport`] about it.
+pub const FAILURE_REPORT: &str = "FAILURE_REPORT";
+
+/// The most a message or field can carry. Lengths come in off a socket or a
+/// file, a garbled one shouldn't get to allocate gigabytes.
+pub const MAX_PAYLOAD: usize = 64 * 1024 * 1024;
+
+fn read_payload(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_PAYLOAD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("a {len} byte message is over the {MAX_PAYLOAD} byte limit"),
+        ));
+    }
+    let mut payload = vec![0; len];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// What the in-game editor asks the watcher to do with the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatcherCommand {
+    /// Rebuild and hot reload the game without restarting it.
+    Rebuild,
+    /// The game is about to exit, start it again normally.
+    Restart,
+    /// The game is about to exit, start it again with `ONLY_UI` set.
+    RestartOnlyUi,
+    /// The game is about to exit, don't start it again.
+    Stop,
+}
+
+impl WatcherCommand {
+    pub const ALL: [WatcherCommand; 4] = [
+        WatcherCommand::Rebuild,
+        WatcherCommand::Restart,
+        WatcherCommand::RestartOnlyUi,
+        WatcherCommand::Stop,
+    ];
+
+    fn to_byte(self) -> u8 {
+        match self {
+            WatcherCommand::Rebuild => 0,
+            // `1` is what "turn ONLY_UI off" always was
+            WatcherCommand::Restart => 1,
+            WatcherCommand::RestartOnlyUi => 2,
+            WatcherCommand::Stop => 3,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|command| command.to_byte() == byte)
+    }
+
+    /// Whether the game has to exit for the watcher to carry this out.
+    pub fn needs_exit(self) -> bool {
+        self != WatcherCommand::Rebuild
+    }
+}
+
+/// What the in-game editor sends back to the watcher. Every message is a tag
+/// byte, a little endian `u32` payload length and the payload.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditorToWatcher {
+    Command(WatcherCommand),
+    /// Forwarded as-is to the game process' stdin.
+    Stdin(Vec<u8>),
+}
+
+impl EditorToWatcher {
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let command;
+        let (tag, payload) = match self {
+            EditorToWatcher::Command(c) => {
+                command = [c.to_byte()];
+                (0u8, command.as_slice())
+            }
+            EditorToWatcher::Stdin(bytes) => (1u8, bytes.as_slice()),
+        };
+        writer.write_all(&[tag])?;
+        writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+        writer.write_all(payload)?;
+        writer.flush()
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut tag = [0];
+        reader.read_exact(&mut tag)?;
+        let payload = read_payload(reader)?;
+        match tag[0] {
+            0 => payload
+                .first()
+                .copied()
+                .and_then(WatcherCommand::from_byte)
+                .map(EditorToWatcher::Command)
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "unknown watcher command")
+                }),
+            1 => Ok(EditorToWatcher::Stdin(payload)),
+            tag => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown editor message tag {tag}"),
+            )),
+        }
+    }
+}
+
+/// A build of the game's code, as the watcher sees it in cargo's output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildEvent {
+    Started,
+    Succeeded,
+    Failed,
+}
+
+impl BuildEvent {
+    fn to_byte(self) -> u8 {
+        match self {
+            BuildEvent::Started => 0,
+            BuildEvent::Succeeded => 1,
+            BuildEvent::Failed => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        [BuildEvent::Started, BuildEvent::Succeeded, BuildEvent::Failed]
+            .into_iter()
+            .find(|event| event.to_byte() == byte)
+    }
+}
+
+/// What the watcher streams to the in-game editor, framed like [`EditorToWatcher`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatcherToEditor {
+    /// Whatever the game and its builds printed.
+    Output(Vec<u8>),
+    /// `at` is in milliseconds since the unix epoch, so replays to a late
+    /// connection still tell how long ago it happened.
+    Build { event: BuildEvent, at: u64 },
+}
+
+impl WatcherToEditor {
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let build;
+        let (tag, payload) = match self {
+            WatcherToEditor::Output(bytes) => (0u8, bytes.as_slice()),
+            WatcherToEditor::Build { event, at } => {
+                let mut bytes = [0; 9];
+                bytes[0] = event.to_byte();
+                bytes[1..].copy_from_slice(&at.to_le_bytes());
+                build = bytes;
+                (1u8, build.as_slice())
+            }
+        };
+        writer.write_all(&[tag])?;
+        writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+        writer.write_all(payload)?;
+        writer.flush()
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let mut tag = [0];
+        reader.read_exact(&mut tag)?;
+        let payload = read_payload(reader)?;
+        match tag[0] {
+            0 => Ok(WatcherToEditor::Output(payload)),
+            1 => {
+                let event = payload.first().copied().and_then(BuildEvent::from_byte);
+                let at = payload.get(1..9).and_then(|at| at.try_into().ok());
+                match (event, at) {
+                    (Some(event), Some(at)) => Ok(WatcherToEditor::Build {
+                        event,
+                        at: u64::from_le_bytes(at),
+                    }),
+                    _ => Err(io::Error::new(io::ErrorKind::InvalidData, "bad build event")),
+                }
+            }
+            tag => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown watcher message tag {tag}"),
+            )),
+        }
+    }
+}
+
+/// Why the previous run went down: how it exited and everything it printed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureReport {
+    pub status: String,
+    pub output: Vec<u8>,
+}
+
+impl FailureReport {
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        // the end of the output is where the error is
+        let output = &self.output[self.output.len().saturating_sub(MAX_PAYLOAD)..];
+        for field in [self.status.as_bytes(), output] {
+            writer.write_all(&(field.len() as u32).to_le_bytes())?;
+            writer.write_all(field)?;
+        }
+        writer.flush()
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        let status = String::from_utf8_lossy(&read_payload(reader)?).into_owned();
+        let output = read_payload(reader)?;
+        Ok(FailureReport { status, output })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut bytes = Vec::new();
+        EditorToWatcher::Stdin(b"hello\n".to_vec()).write_to(&mut bytes).unwrap();
+        EditorToWatcher::Command(WatcherCommand::Stop).write_to(&mut bytes).unwrap();
+        let mut reader = bytes.as_slice();
+        assert_eq!(
+            EditorToWatcher::read_from(&mut reader).unwrap(),
+            EditorToWatcher::Stdin(b"hello\n".to_vec())
+        );
+        assert_eq!(
+            EditorToWatcher::read_from(&mut reader).unwrap(),
+            EditorToWatcher::Command(WatcherCommand::Stop)
+        );
+    }
+
+    #[test]
+    fn oversized_lengths_are_refused_before_allocating() {
+        let mut bytes = vec![0];
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        let err = WatcherToEditor::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = FailureReport::read_from(&mut &bytes[1..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,645 @@
+use std::collections::VecDeque;
+
+pub const DEFAULT_COLUMNS: usize = 200;
+pub const DEFAULT_ROWS: usize = 50;
+pub const DEFAULT_SCROLLBACK: usize = 10_000;
+const TAB_WIDTH: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Color {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// `None` means "whatever the ui uses for default text/background".
+    pub fn to_rgb(self) -> Option<[u8; 3]> {
+        match self {
+            Color::Default => None,
+            Color::Indexed(index) => Some(indexed_rgb(index)),
+            Color::Rgb(r, g, b) => Some([r, g, b]),
+        }
+    }
+}
+
+fn indexed_rgb(index: u8) -> [u8; 3] {
+    const ANSI: [[u8; 3]; 16] = [
+        [0, 0, 0],
+        [205, 49, 49],
+        [13, 188, 121],
+        [229, 229, 16],
+        [36, 114, 200],
+        [188, 63, 188],
+        [17, 168, 205],
+        [229, 229, 229],
+        [102, 102, 102],
+        [241, 76, 76],
+        [35, 209, 139],
+        [245, 245, 67],
+        [59, 142, 234],
+        [214, 112, 214],
+        [41, 184, 219],
+        [255, 255, 255],
+    ];
+    match index {
+        0..=15 => ANSI[index as usize],
+        16..=231 => {
+            let index = index - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            [level(index / 36), level((index / 6) % 6), level(index % 6)]
+        }
+        _ => {
+            let v = 8 + (index - 232) * 10;
+            [v, v, v]
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub inverse: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cell {
+    pub c: char,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            c: ' ',
+            style: Style::default(),
+        }
+    }
+}
+
+pub type Line = Vec<Cell>;
+
+pub fn line_text(line: &Line) -> String {
+    let text = line.iter().map(|cell| cell.c).collect::<String>();
+    text.trim_end().to_string()
+}
+
+/// A VT100-ish terminal: a cell grid whose bottom `rows` lines are the
+/// addressable screen and everything above it is scrollback.
+pub struct Terminal {
+    parser: vte::Parser,
+    grid: Grid,
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Terminal::new(DEFAULT_COLUMNS, DEFAULT_ROWS, DEFAULT_SCROLLBACK)
+    }
+}
+
+impl Terminal {
+    pub fn new(columns: usize, rows: usize, scrollback: usize) -> Self {
+        let mut lines = VecDeque::new();
+        lines.push_back(Line::new());
+        Terminal {
+            parser: vte::Parser::new(),
+            grid: Grid {
+                lines,
+                columns: columns.max(1),
+                rows: rows.max(1),
+                scrollback: scrollback.max(rows.max(1)),
+                cursor_row: 0,
+                cursor_col: 0,
+                saved_cursor: None,
+                scroll_region: None,
+                style: Style::default(),
+                dropped: 0,
+            },
+        }
+    }
+
+    pub fn advance(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.parser.advance(&mut self.grid, *byte);
+        }
+    }
+
+    pub fn lines(&self) -> &VecDeque<Line> {
+        &self.grid.lines
+    }
+
+    /// How many lines have been dropped off the front of the scrollback so far,
+    /// i.e. the absolute line number of `lines()[0]`.
+    pub fn first_line_number(&self) -> usize {
+        self.grid.dropped
+    }
+
+    /// Index into `lines()` of the first line that can still be rewritten by
+    /// cursor movement. Everything above it is settled scrollback.
+    pub fn screen_top(&self) -> usize {
+        self.grid.screen_top()
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for line in &self.grid.lines {
+            text.push_str(&line_text(line));
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn columns(&self) -> usize {
+        self.grid.columns
+    }
+
+    pub fn scrollback(&self) -> usize {
+        self.grid.scrollback
+    }
+
+    /// Caps how many lines are kept, dropping the oldest ones right away if
+    /// there are already more than that.
+    pub fn set_scrollback(&mut self, scrollback: usize) {
+        self.grid.scrollback = scrollback.max(self.grid.rows);
+        self.grid.trim_scrollback();
+    }
+
+    pub fn clear(&mut self) {
+        self.grid.reset();
+    }
+}
+
+struct Grid {
+    lines: VecDeque<Line>,
+    columns: usize,
+    rows: usize,
+    scrollback: usize,
+    /// Index into `lines`, not relative to the screen.
+    cursor_row: usize,
+    cursor_col: usize,
+    saved_cursor: Option<(usize, usize)>,
+    /// Top and bottom screen rows, inclusive, that line feeds scroll within
+    /// (`CSI t;b r`). `None` is the whole screen, with scrollback.
+    scroll_region: Option<(usize, usize)>,
+    style: Style,
+    dropped: usize,
+}
+
+impl Grid {
+    /// Everything, scrollback included, like `ESC c`.
+    fn reset(&mut self) {
+        self.dropped += self.lines.len();
+        self.lines.clear();
+        self.lines.push_back(Line::new());
+        self.cursor_row = 0;
+        self.cursor_col = 0;
+        self.saved_cursor = None;
+        self.scroll_region = None;
+        self.style = Style::default();
+    }
+
+    fn screen_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.rows)
+    }
+
+    fn line_feed(&mut self) {
+        if let Some((top, bottom)) = self.scroll_region {
+            let screen_top = self.screen_top();
+            if self.cursor_row == screen_top + bottom {
+                // lines scrolled out of a region are gone, not scrollback
+                self.lines.remove(screen_top + top);
+                self.lines.insert(screen_top + bottom, Line::new());
+            } else if self.cursor_row + 1 < self.lines.len() {
+                self.cursor_row += 1;
+            }
+            return;
+        }
+        self.cursor_row += 1;
+        while self.cursor_row >= self.lines.len() {
+            self.lines.push_back(Line::new());
+        }
+        self.trim_scrollback();
+    }
+
+    fn trim_scrollback(&mut self) {
+        while self.lines.len() > self.scrollback {
+            self.lines.pop_front();
+            self.dropped += 1;
+            self.cursor_row = self.cursor_row.saturating_sub(1);
+            if let Some((row, _)) = self.saved_cursor.as_mut() {
+                *row = row.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Makes all of the screen's rows exist, so they can be addressed.
+    fn fill_screen(&mut self) {
+        while self.lines.len() < self.rows {
+            self.lines.push_back(Line::new());
+        }
+    }
+
+    fn move_to_screen_row(&mut self, row: usize) {
+        let row = row.min(self.rows - 1);
+        self.fill_screen();
+        self.cursor_row = self.screen_top() + row;
+    }
+
+    /// Puts the cursor back where `ESC 7`/`CSI s` left it, or at the top of
+    /// the screen if that has scrolled off it since.
+    fn restore_cursor(&mut self) {
+        if let Some((row, col)) = self.saved_cursor {
+            self.cursor_row = row.clamp(self.screen_top(), self.lines.len() - 1);
+            self.cursor_col = col;
+        }
+    }
+
+    fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        let bottom = bottom.min(self.rows);
+        self.scroll_region = (top >= 1 && top < bottom && (top, bottom) != (1, self.rows))
+            .then(|| (top - 1, bottom - 1));
+        self.move_to_screen_row(0);
+        self.cursor_col = 0;
+    }
+
+    fn current_line(&mut self) -> &mut Line {
+        &mut self.lines[self.cursor_row]
+    }
+
+    fn blank(&self) -> Cell {
+        Cell {
+            c: ' ',
+            style: Style {
+                bg: self.style.bg,
+                ..Style::default()
+            },
+        }
+    }
+
+    fn put(&mut self, c: char) {
+        if self.cursor_col >= self.columns {
+            self.line_feed();
+            self.cursor_col = 0;
+        }
+        let cell = Cell {
+            c,
+            style: self.style,
+        };
+        let blank = self.blank();
+        let col = self.cursor_col;
+        let line = self.current_line();
+        if line.len() <= col {
+            line.resize(col, blank);
+            line.push(cell);
+        } else {
+            line[col] = cell;
+        }
+        self.cursor_col += 1;
+    }
+
+    fn erase_in_line(&mut self, mode: u16) {
+        let col = self.cursor_col;
+        let blank = self.blank();
+        let line = self.current_line();
+        match mode {
+            0 => line.truncate(col),
+            1 => {
+                let end = (col + 1).min(line.len());
+                line[..end].fill(blank);
+            }
+            _ => line.clear(),
+        }
+    }
+
+    fn erase_in_display(&mut self, mode: u16) {
+        match mode {
+            0 => {
+                self.erase_in_line(0);
+                // the screen keeps its size, or it'd slide up into the scrollback
+                for row in self.cursor_row + 1..self.lines.len() {
+                    self.lines[row].clear();
+                }
+            }
+            1 => {
+                for row in self.screen_top()..self.cursor_row {
+                    self.lines[row].clear();
+                }
+                self.erase_in_line(1);
+            }
+            2 => {
+                for row in self.screen_top()..self.lines.len() {
+                    self.lines[row].clear();
+                }
+            }
+            _ => {
+                let screen_top = self.screen_top();
+                self.lines.drain(..screen_top);
+                self.dropped += screen_top;
+                self.cursor_row = self.cursor_row.saturating_sub(screen_top);
+                self.saved_cursor = None;
+            }
+        }
+    }
+
+    fn sgr(&mut self, params: &vte::Params) {
+        if params.is_empty() {
+            self.style = Style::default();
+            return;
+        }
+        let mut iter = params.iter();
+        while let Some(param) = iter.next() {
+            let style = &mut self.style;
+            match param {
+                [0] => *style = Style::default(),
+                [1] => style.bold = true,
+                [2] => style.dim = true,
+                [3] => style.italic = true,
+                [4, 0] => style.underline = false,
+                [4] | [4, _] => style.underline = true,
+                [7] => style.inverse = true,
+                [9] => style.strikethrough = true,
+                [21] | [22] => {
+                    style.bold = false;
+                    style.dim = false;
+                }
+                [23] => style.italic = false,
+                [24] => style.underline = false,
+                [27] => style.inverse = false,
+                [29] => style.strikethrough = false,
+                [n @ 30..=37] => style.fg = Color::Indexed((n - 30) as u8),
+                [38, rest @ ..] => {
+                    if let Some(color) = extended_color(rest, &mut iter) {
+                        style.fg = color;
+                    }
+                }
+                [39] => style.fg = Color::Default,
+                [n @ 40..=47] => style.bg = Color::Indexed((n - 40) as u8),
+                [48, rest @ ..] => {
+                    if let Some(color) = extended_color(rest, &mut iter) {
+                        style.bg = color;
+                    }
+                }
+                [49] => style.bg = Color::Default,
+                [n @ 90..=97] => style.fg = Color::Indexed((n - 90 + 8) as u8),
+                [n @ 100..=107] => style.bg = Color::Indexed((n - 100 + 8) as u8),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parses the tail of a `38`/`48` SGR, either in the colon form (`38:2:r:g:b`,
+/// handed to us as subparams) or the common semicolon form (`38;5;n`).
+fn extended_color<'a>(
+    subparams: &[u16],
+    iter: &mut impl Iterator<Item = &'a [u16]>,
+) -> Option<Color> {
+    let mut values = vec![];
+    if subparams.is_empty() {
+        let kind = iter.next()?.first().copied()?;
+        values.push(kind);
+        let count = if kind == 5 { 1 } else { 3 };
+        for _ in 0..count {
+            values.push(iter.next()?.first().copied()?);
+        }
+    } else {
+        values.extend_from_slice(subparams);
+    }
+    match values.as_slice() {
+        [5, index, ..] => Some(Color::Indexed(*index as u8)),
+        [2, r, g, b] | [2, _, r, g, b] => Some(Color::Rgb(*r as u8, *g as u8, *b as u8)),
+        _ => None,
+    }
+}
+
+fn param(params: &vte::Params, index: usize, default: u16) -> u16 {
+    match params.iter().nth(index).and_then(|p| p.first().copied()) {
+        None | Some(0) => default,
+        Some(value) => value,
+    }
+}
+
+impl vte::Perform for Grid {
+    fn print(&mut self, c: char) {
+        self.put(c);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            // output comes through a pipe, so nothing translated `\n` into `\r\n` for us
+            b'\n' | 0x0b | 0x0c => {
+                self.line_feed();
+                self.cursor_col = 0;
+            }
+            b'\r' => self.cursor_col = 0,
+            0x08 => self.cursor_col = self.cursor_col.saturating_sub(1),
+            b'\t' => {
+                let next = (self.cursor_col / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.cursor_col = next.min(self.columns - 1);
+            }
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(
+        &mut self,
+        params: &vte::Params,
+        intermediates: &[u8],
+        ignore: bool,
+        action: char,
+    ) {
+        // private modes (`?25l` and friends) only matter for interactive programs
+        if ignore || !intermediates.is_empty() {
+            return;
+        }
+        let screen_row = self.cursor_row - self.screen_top().min(self.cursor_row);
+        match action {
+            'm' => self.sgr(params),
+            'K' => self.erase_in_line(param(params, 0, 0)),
+            'J' => self.erase_in_display(param(params, 0, 0)),
+            'A' => {
+                let n = param(params, 0, 1) as usize;
+                self.cursor_row = self
+                    .cursor_row
+                    .saturating_sub(n)
+                    .max(self.screen_top());
+            }
+            'B' => {
+                let n = param(params, 0, 1) as usize;
+                self.move_to_screen_row(screen_row + n);
+            }
+            'C' => {
+                let n = param(params, 0, 1) as usize;
+                self.cursor_col = (self.cursor_col + n).min(self.columns - 1);
+            }
+            'D' => {
+                let n = param(params, 0, 1) as usize;
+                self.cursor_col = self.cursor_col.saturating_sub(n);
+            }
+            'E' => {
+                let n = param(params, 0, 1) as usize;
+                self.move_to_screen_row(screen_row + n);
+                self.cursor_col = 0;
+            }
+            'F' => {
+                let n = param(params, 0, 1) as usize;
+                self.move_to_screen_row(screen_row.saturating_sub(n));
+                self.cursor_col = 0;
+            }
+            'G' => {
+                let col = param(params, 0, 1) as usize;
+                self.cursor_col = (col - 1).min(self.columns - 1);
+            }
+            'H' | 'f' => {
+                let row = param(params, 0, 1) as usize;
+                let col = param(params, 1, 1) as usize;
+                self.move_to_screen_row(row - 1);
+                self.cursor_col = (col - 1).min(self.columns - 1);
+            }
+            's' => self.saved_cursor = Some((self.cursor_row, self.cursor_col)),
+            'u' => self.restore_cursor(),
+            'r' => {
+                let top = param(params, 0, 1) as usize;
+                let bottom = param(params, 1, self.rows as u16) as usize;
+                self.set_scroll_region(top, bottom);
+            }
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        if !intermediates.is_empty() {
+            return;
+        }
+        match byte {
+            b'7' => self.saved_cursor = Some((self.cursor_row, self.cursor_col)),
+            b'8' => self.restore_cursor(),
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.line_feed();
+                self.cursor_col = 0;
+            }
+            b'M' => {
+                let screen_top = self.screen_top();
+                match self.scroll_region {
+                    Some((top, bottom)) if self.cursor_row == screen_top + top => {
+                        self.lines.remove(screen_top + bottom);
+                        self.lines.insert(screen_top + top, Line::new());
+                    }
+                    _ => {
+                        self.cursor_row = self.cursor_row.saturating_sub(1).max(screen_top);
+                    }
+                }
+            }
+            b'c' => self.reset(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(terminal: &Terminal) -> Vec<String> {
+        terminal.lines().iter().skip(terminal.screen_top()).map(line_text).collect()
+    }
+
+    fn numbered_lines(terminal: &mut Terminal, count: usize) {
+        for n in 0..count {
+            terminal.advance(format!("line {n}\n").as_bytes());
+        }
+    }
+
+    #[test]
+    fn erase_in_line() {
+        let mut terminal = Terminal::new(80, 5, 100);
+        terminal.advance(b"abcdef\x1b[3D\x1b[K");
+        assert_eq!(line_text(&terminal.lines()[0]), "abc");
+        terminal.advance(b"\rabcdef\x1b[3D\x1b[1K");
+        assert_eq!(line_text(&terminal.lines()[0]), "    ef");
+        terminal.advance(b"\x1b[2K");
+        assert_eq!(line_text(&terminal.lines()[0]), "");
+    }
+
+    #[test]
+    fn erase_below_keeps_the_screen_in_place() {
+        let mut terminal = Terminal::new(80, 5, 1000);
+        numbered_lines(&mut terminal, 20);
+        terminal.advance(b"\x1b[2A\x1b[J\x1b[Hx");
+        assert_eq!(terminal.lines().len(), 21);
+        assert_eq!(line_text(&terminal.lines()[14]), "line 14");
+        assert_eq!(line_text(&terminal.lines()[15]), "line 15");
+        assert_eq!(screen(&terminal), ["xine 16", "line 17", "", "", ""]);
+    }
+
+    #[test]
+    fn erase_above_and_whole_screen() {
+        let mut terminal = Terminal::new(80, 3, 100);
+        numbered_lines(&mut terminal, 5);
+        terminal.advance(b"\x1b[2;3H\x1b[1J");
+        assert_eq!(screen(&terminal), ["", "   e 4", ""]);
+        assert_eq!(line_text(&terminal.lines()[2]), "line 2");
+        terminal.advance(b"\x1b[2J");
+        assert_eq!(screen(&terminal), ["", "", ""]);
+        assert_eq!(line_text(&terminal.lines()[2]), "line 2");
+    }
+
+    #[test]
+    fn erase_scrollback() {
+        let mut terminal = Terminal::new(80, 3, 100);
+        numbered_lines(&mut terminal, 5);
+        terminal.advance(b"\x1b[3Jx");
+        assert_eq!(terminal.first_line_number(), 3);
+        assert_eq!(screen(&terminal), ["line 3", "line 4", "x"]);
+    }
+
+    #[test]
+    fn erase_scrollback_after_restoring_into_it() {
+        let mut terminal = Terminal::new(80, 5, 1000);
+        terminal.advance(b"\x1b7");
+        numbered_lines(&mut terminal, 20);
+        terminal.advance(b"\x1b8\x1b[3Jx");
+        assert_eq!(screen(&terminal), ["xine 16", "line 17", "line 18", "line 19", ""]);
+    }
+
+    #[test]
+    fn save_and_restore_cursor() {
+        let mut terminal = Terminal::new(80, 5, 100);
+        terminal.advance(b"ab\x1b7cd\nxy\x1b8Z");
+        assert_eq!(screen(&terminal)[..2], ["abZd", "xy"]);
+        terminal.advance(b"\x1b[2;2H\x1b[s\x1b[H\x1b[uQ");
+        assert_eq!(screen(&terminal)[..2], ["abZd", "xQ"]);
+    }
+
+    #[test]
+    fn restore_scrolled_off_cursor_goes_to_the_top() {
+        let mut terminal = Terminal::new(80, 3, 100);
+        terminal.advance(b"\x1b[s");
+        numbered_lines(&mut terminal, 6);
+        terminal.advance(b"\x1b[uX");
+        assert_eq!(line_text(&terminal.lines()[0]), "line 0");
+        assert_eq!(screen(&terminal), ["Xine 4", "line 5", ""]);
+    }
+
+    #[test]
+    fn scroll_region() {
+        let mut terminal = Terminal::new(80, 5, 100);
+        terminal.advance(b"1\n2\n3\n4\n5");
+        terminal.advance(b"\x1b[2;4r\x1b[4;1H\nX");
+        assert_eq!(screen(&terminal), ["1", "3", "4", "X", "5"]);
+        terminal.advance(b"\x1b[2;1H\x1bM");
+        assert_eq!(screen(&terminal), ["1", "", "3", "4", "5"]);
+        // back to the whole screen, scrolling into the scrollback again
+        terminal.advance(b"\x1b[r\x1b[5;1H\nY");
+        assert_eq!(screen(&terminal), ["", "3", "4", "5", "Y"]);
+        assert_eq!(line_text(&terminal.lines()[0]), "1");
+    }
+}
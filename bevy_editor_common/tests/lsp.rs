@@ -0,0 +1,90 @@
+#![cfg(feature = "lsp")]
+
+use bevy_editor_common::lsp::{LspClient, LspEvent, Position, Range, Severity};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+fn stub() -> LspClient {
+    LspClient::spawn(Command::new(env!("CARGO_BIN_EXE_lsp_stub")), Path::new("/project")).unwrap()
+}
+
+/// Polls until the server sends something, the client doesn't block on its own.
+fn next_event(client: &mut LspClient) -> LspEvent {
+    let started = Instant::now();
+    loop {
+        if let Some(event) = client.poll().into_iter().next() {
+            return event;
+        }
+        assert!(started.elapsed() < Duration::from_secs(10), "the stub server didn't answer");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn syncs_documents_and_gets_diagnostics() {
+    let mut client = stub();
+    let path = PathBuf::from("/project/src/lib.rs");
+    // sent before the server is initialized, so it's held back until then
+    client.did_open(&path, "rust", "fn main() {}");
+    assert_eq!(next_event(&mut client), LspEvent::Diagnostics(path.clone()));
+    let diagnostic = &client.diagnostics(&path)[0];
+    assert_eq!(diagnostic.message, "v0: fn main() {}");
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(diagnostic.source.as_deref(), Some("stub"));
+
+    client.did_change(&path, "fn main() { todo!() }");
+    assert_eq!(next_event(&mut client), LspEvent::Diagnostics(path.clone()));
+    assert_eq!(client.diagnostics(&path)[0].message, "v1: fn main() { todo!() }");
+    assert_eq!(client.all_diagnostics().len(), 1);
+
+    client.did_close(&path);
+    assert!(client.diagnostics(&path).is_empty());
+}
+
+#[test]
+fn hover_definition_and_completion() {
+    let mut client = stub();
+    let path = PathBuf::from("/project/src/lib.rs");
+    let position = Position { line: 3, character: 7 };
+
+    client.hover(&path, position);
+    let expected = LspEvent::Hover { path: path.clone(), position, text: Some("hover at 3:7".to_string()) };
+    assert_eq!(next_event(&mut client), expected);
+
+    client.definition(&path, position);
+    let LspEvent::Definition { locations, .. } = next_event(&mut client) else {
+        panic!("expected a definition");
+    };
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations[0].path, path);
+    assert_eq!(locations[0].range, Range { start: position, end: position });
+
+    client.completion(&path, position);
+    let LspEvent::Completion { items, .. } = next_event(&mut client) else {
+        panic!("expected completions");
+    };
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].label, "stub_item");
+    assert_eq!(items[0].detail.as_deref(), Some("from the stub"));
+    assert_eq!(items[0].insert_text, "stub_item()");
+}
+
+#[test]
+fn a_server_that_stops_reading_doesnt_block_the_caller() {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_lsp_stub"));
+    command.arg("--stop-reading");
+    let mut client = LspClient::spawn(command, Path::new("/project")).unwrap();
+    assert_eq!(next_event(&mut client), LspEvent::Diagnostics(PathBuf::from("/project/stopped-reading")));
+
+    // far more than a pipe holds
+    let path = PathBuf::from("/project/src/lib.rs");
+    let text = "x".repeat(64 * 1024);
+    let started = Instant::now();
+    client.did_open(&path, "rust", &text);
+    for _ in 0..100 {
+        client.did_change(&path, &text);
+    }
+    drop(client);
+    assert!(started.elapsed() < Duration::from_secs(5), "writing to the server blocked");
+}
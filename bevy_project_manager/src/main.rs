@@ -1,8 +1,12 @@
+mod settings;
 mod templates;
-mod utils;
 
+use crate::settings::ManagerSettings;
 use crate::templates::Template;
-use crossbeam_channel::Receiver;
+use bevy_editor_common::config::{EditorConfig, WindowPlacement, SCENES_DIR};
+use bevy_editor_common::egui_27::{display_terminal, TerminalView};
+use bevy_editor_common::process::{command_channels, write_stdin, OutputReceiver};
+use bevy_editor_common::vt;
 use directories::ProjectDirs;
 use eframe::emath::{Align, Vec2};
 use eframe::{NativeOptions, WindowBuilder};
@@ -31,6 +35,10 @@ fn main() {
 
 
 
+    // the watcher finds it next to itself
+    templates::Template::editor_common()
+        .build_template(cache_pos.join("bevy_editor_common"))
+        .unwrap();
     cache_pos.push("hotreload_watcher");
     templates::Template::hot_reload_watcher()
         .build_template(cache_pos)
@@ -70,13 +78,17 @@ pub enum AppStates {
 
 pub struct DexterousDevInstall {
     child: Child,
-    buf: String,
-    rx: Receiver<u8>,
+    terminal: vt::Terminal,
+    terminal_view: TerminalView,
+    rx: OutputReceiver,
 }
 
 impl DexterousDevInstall {
     fn ui(&mut self, ui: &mut Ui) {
-        utils::display_terminal(&mut self.buf, self.rx.clone(), ui);
+        let input = display_terminal(&mut self.terminal_view, &mut self.terminal, &self.rx, ui);
+        if let Some(line) = input {
+            write_stdin(&mut self.child, &line);
+        }
     }
 }
 
@@ -85,11 +97,12 @@ impl Default for DexterousDevInstall {
         let mut command = Command::new("cargo");
         command.arg("install").arg("dexterous_developer_cli");
 
-        let (rx, child) = utils::command_channels(command);
+        let (rx, child) = command_channels(command, true).unwrap();
 
         DexterousDevInstall {
             child,
-            buf: "".to_string(),
+            terminal: vt::Terminal::default(),
+            terminal_view: TerminalView::default(),
             rx,
         }
     }
@@ -97,9 +110,13 @@ impl Default for DexterousDevInstall {
 
 pub struct ProjectRunner {
     running: ProjectItem,
-    terminal_string: String,
+    settings: ManagerSettings,
+    /// Why the last clicked location didn't open.
+    open_error: Option<String>,
+    terminal: vt::Terminal,
+    terminal_view: TerminalView,
     first_run: bool,
-    rx: Option<Receiver<u8>>,
+    rx: Option<OutputReceiver>,
     child: Option<Child>,
 }
 
@@ -112,11 +129,34 @@ impl ProjectRunner {
             command.arg("--");
             command.arg(self.running.dir_entry.path());
             command.current_dir(get_hotreload_dir());
-            let (rx, child) = utils::command_channels(command);
+            let (rx, child) = command_channels(command, true).unwrap();
             self.rx.replace(rx);
             self.child.replace(child);
         }
-        utils::display_terminal(&mut self.terminal_string, self.rx.clone().unwrap(), ui);
+        if let Some(err) = self.open_error.clone() {
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+                if ui.small_button(egui_phosphor::regular::X).clicked() {
+                    self.open_error = None;
+                }
+            });
+        }
+        let input = display_terminal(
+            &mut self.terminal_view,
+            &mut self.terminal,
+            self.rx.as_ref().unwrap(),
+            ui,
+        );
+        if let (Some(line), Some(child)) = (input, self.child.as_mut()) {
+            write_stdin(child, &line);
+        }
+        if let Some(location) = self.terminal_view.clicked_location() {
+            let project_dir = self.running.dir_entry.path();
+            self.open_error = self
+                .settings
+                .open_in_external_editor(&location, &project_dir)
+                .err();
+        }
     }
 }
 
@@ -133,6 +173,11 @@ struct ProjectViewer {
     create_project_text: String,
     selected_template: Templates,
     switch_selected: Option<usize>,
+    settings: ManagerSettings,
+    settings_status: Option<String>,
+    /// The project being edited, with its editor config.
+    editing: Option<(PathBuf, EditorConfig)>,
+    edit_status: Option<String>,
 }
 
 impl Default for ProjectViewer {
@@ -146,6 +191,10 @@ impl Default for ProjectViewer {
             create_project_text: "".to_string(),
             selected_template: Templates::StandardHotReloadTemplate,
             switch_selected: None,
+            settings: ManagerSettings::load(),
+            settings_status: None,
+            editing: None,
+            edit_status: None,
         };
         project_viewer.scan();
         project_viewer
@@ -213,6 +262,7 @@ impl ProjectViewer {
                             .unwrap();
                         file.write_all(template.contents).unwrap();
                     }
+                    templates::vendor_editor_common(&path_buf).unwrap();
                     new_project_popup.close();
                     self.scan();
                 }
@@ -247,6 +297,56 @@ impl ProjectViewer {
                 }
             });
         });
+        let edit_project_popup = Modal::new(ui.ctx(), "edit project modal");
+        edit_project_popup.show(|ui| {
+            edit_project_popup.title(ui, format!("Edit {}", self.dropdown_buf_field));
+            edit_project_popup.frame(ui, |ui| {
+                if let Some((_, config)) = self.editing.as_mut() {
+                    ui.label("Editor window");
+                    ui.radio_value(&mut config.window, WindowPlacement::GameWindow, "over the game, in its window");
+                    ui.radio_value(&mut config.window, WindowPlacement::NewWindow, "in a window of its own");
+                    ui.horizontal(|ui| {
+                        let fullscreen = matches!(config.window, WindowPlacement::Fullscreen { .. });
+                        if ui.radio(fullscreen, "borderless fullscreen on monitor").clicked() && !fullscreen {
+                            config.window = WindowPlacement::Fullscreen { monitor: 1 };
+                        }
+                        if let WindowPlacement::Fullscreen { monitor } = &mut config.window {
+                            ui.add(egui::DragValue::new(monitor).clamp_range(0..=8))
+                                .on_hover_text("0 is the first monitor");
+                        }
+                    });
+                    ui.add_space(4.0);
+                    ui.label("Startup scene");
+                    let mut scene = config.scene.clone().unwrap_or_default();
+                    if ui
+                        .add(egui::TextEdit::singleline(&mut scene).hint_text("none, the game's setup"))
+                        .on_hover_text(format!("saved from the editor's Scenes window to {SCENES_DIR}, without .scn.ron"))
+                        .changed()
+                    {
+                        config.scene = (!scene.is_empty()).then_some(scene);
+                    }
+                }
+                if let Some(status) = &self.edit_status {
+                    ui.label(status);
+                }
+            });
+            edit_project_popup.buttons(ui, |ui| {
+                if ui.button("Close").clicked() {
+                    edit_project_popup.close();
+                }
+                if ui
+                    .add_enabled(self.editing.is_some(), egui::Button::new("Save"))
+                    .clicked()
+                {
+                    if let Some((project_dir, config)) = &self.editing {
+                        self.edit_status = Some(match config.save(project_dir) {
+                            Ok(()) => "saved, it's used the next time the project runs".to_string(),
+                            Err(err) => err,
+                        });
+                    }
+                }
+            });
+        });
         let rect = ui.label("projects").rect;
         ui.add(
             DropDownBox::from_iter(
@@ -300,7 +400,23 @@ impl ProjectViewer {
                     if ui
                         .add_enabled(self.selected_item.is_some(), egui::Button::new("Edit"))
                         .clicked()
-                    {}
+                    {
+                        if let Some(item) = self.selected_item.and_then(|index| self.items_list.get(index)) {
+                            let project_dir = item.dir_entry.path();
+                            // a config that doesn't parse isn't overwritten from here
+                            match EditorConfig::load(&project_dir) {
+                                Ok(config) => {
+                                    self.editing = Some((project_dir, config));
+                                    self.edit_status = None;
+                                }
+                                Err(err) => {
+                                    self.editing = None;
+                                    self.edit_status = Some(err);
+                                }
+                            }
+                            edit_project_popup.open();
+                        }
+                    }
                     if ui
                         .add_enabled(self.selected_item.is_some(), egui::Button::new("Run"))
                         .clicked()
@@ -349,6 +465,30 @@ impl ProjectViewer {
             "Standard HotReload Template",
         );
     }
+    pub fn settings(&mut self, ui: &mut Ui) {
+        ui.label("External editor");
+        ui.add(
+            egui::TextEdit::singleline(&mut self.settings.external_editor)
+                .hint_text("code --goto {file}:{line}:{column}")
+                .desired_width(f32::INFINITY),
+        );
+        ui.weak("Opens the file:line:column locations clicked in a running project's terminal. {file}, {line} and {column} are filled in, quote paths with spaces.");
+        ui.add_space(20.0);
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                self.settings_status = Some(match self.settings.save() {
+                    Ok(()) => "saved".to_string(),
+                    Err(err) => err,
+                });
+            }
+            if ui.button("Reset to default").clicked() {
+                self.settings = ManagerSettings::default();
+            }
+            if let Some(status) = &self.settings_status {
+                ui.label(status);
+            }
+        });
+    }
 }
 #[derive(PartialEq)]
 pub enum Templates {
@@ -371,6 +511,7 @@ impl TabViewer for ProjectViewer {
         match tab.as_str() {
             "Projects" => self.projects(ui),
             "Templates" => self.templates(ui),
+            "Settings" => self.settings(ui),
             _ => panic!(),
         }
     }
@@ -378,7 +519,11 @@ impl TabViewer for ProjectViewer {
 
 impl MyApp {
     pub fn new() -> Self {
-        let mut tree = DockState::new(vec!["Projects".to_string(), "Templates".to_string()]);
+        let mut tree = DockState::new(vec![
+            "Projects".to_string(),
+            "Templates".to_string(),
+            "Settings".to_string(),
+        ]);
         Self {
             tree,
             app_states: AppStates::DexterousDevInstall(DexterousDevInstall::default()),
@@ -402,7 +547,10 @@ impl eframe::App for MyApp {
                     let running = project_viewer.items_list.remove(selected);
                     switch_self.replace(AppStates::ProjectRunner(ProjectRunner {
                         running,
-                        terminal_string: "".to_string(),
+                        settings: project_viewer.settings.clone(),
+                        open_error: None,
+                        terminal: vt::Terminal::default(),
+                        terminal_view: TerminalView::default(),
                         first_run: true,
                         rx: None,
                         child: None,
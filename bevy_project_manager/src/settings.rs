@@ -0,0 +1,140 @@
+use bevy_editor_common::failure::Location;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The project manager's own preferences, next to the editor's settings.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ManagerSettings {
+    /// What opens `file:line:column` locations clicked in the terminal, with
+    /// `{file}`, `{line}` and `{column}` filled in. Split up like a shell
+    /// would, so paths with spaces go in quotes.
+    pub external_editor: String,
+}
+
+impl Default for ManagerSettings {
+    fn default() -> Self {
+        ManagerSettings {
+            external_editor: "code --goto {file}:{line}:{column}".to_string(),
+        }
+    }
+}
+
+impl ManagerSettings {
+    fn path() -> Option<PathBuf> {
+        let dirs = ProjectDirs::from("com", "malek", "bevy_editor")?;
+        Some(dirs.config_dir().join("manager_settings.ron"))
+    }
+
+    /// Missing or broken settings just mean the defaults.
+    pub fn load() -> ManagerSettings {
+        let Some(path) = Self::path() else {
+            return ManagerSettings::default();
+        };
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| ron::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("no config directory for this user")?;
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        std::fs::write(&path, text)
+            .map_err(|err| format!("couldn't save the settings to {}: {err}", path.display()))
+    }
+
+    /// Runs the external editor on `location`, relative paths are from `project_dir`.
+    pub fn open_in_external_editor(&self, location: &Location, project_dir: &Path) -> Result<(), String> {
+        let file = project_dir.join(&location.file);
+        let file = file.to_string_lossy();
+        let line = location.line.to_string();
+        let column = location.column.unwrap_or(1).to_string();
+        let mut args = split_args(&self.external_editor)?.into_iter().map(|arg| {
+            arg.replace("{file}", &file)
+                .replace("{line}", &line)
+                .replace("{column}", &column)
+        });
+        let program = args.next().ok_or("no external editor is set up, see the Settings tab")?;
+        Command::new(&program)
+            .args(args)
+            .current_dir(project_dir)
+            .spawn()
+            // reaped in the background, editors usually hand off to a running instance and exit
+            .map(|mut child| {
+                std::thread::spawn(move || child.wait());
+            })
+            .map_err(|err| format!("couldn't run {program}: {err}"))
+    }
+}
+
+/// Splits `command` on whitespace, except inside `'...'` or `"..."`, like a
+/// shell. A backslash escapes the next character, in double quotes only a `"`
+/// or another backslash, so quoted Windows paths stay as they are.
+fn split_args(command: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some('"') | None, '\\') => {
+                let escaped = chars.next().ok_or("the external editor ends in a lone backslash")?;
+                let arg = arg.get_or_insert_with(String::new);
+                if quote.is_some() && !matches!(escaped, '"' | '\\') {
+                    arg.push('\\');
+                }
+                arg.push(escaped);
+            }
+            (Some(_), c) => arg.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, c) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("the external editor has an unclosed quote".to_string());
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_like_a_shell() {
+        let cases: [(&str, &[&str]); 9] = [
+            ("code -g", &["code", "-g"]),
+            ("  code   -g  ", &["code", "-g"]),
+            (r#""/Applications/Sublime Text.app/subl" "{file}""#, &["/Applications/Sublime Text.app/subl", "{file}"]),
+            ("'my editor' --line '{line}'", &["my editor", "--line", "{line}"]),
+            (r"my\ editor --flag", &["my editor", "--flag"]),
+            (r#"editor "" ''"#, &["editor", "", ""]),
+            (r#"echo "say \"hi\" \n" 'a\b'"#, &["echo", r#"say "hi" \n"#, r"a\b"]),
+            (r#"--name="a b"c"#, &["--name=a bc"]),
+            ("", &[]),
+        ];
+        for (command, expected) in cases {
+            assert_eq!(split_args(command).unwrap(), expected, "{command}");
+        }
+    }
+
+    #[test]
+    fn unterminated_quotes_and_escapes_are_errors() {
+        for command in [r#"code "{file}"#, "code '{file}", r"code \"] {
+            assert!(split_args(command).is_err(), "{command}");
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::io;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 pub struct Template<'a> {
@@ -22,10 +22,70 @@ impl Template<'_> {
                 relative_path: PathBuf::from("src/editor_plugin.rs"),
                 contents: include_bytes!("../../bevy_project_template/src/editor_plugin.rs")
             },
+            FileTemplate {
+                relative_path: PathBuf::from("src/buffer.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/buffer.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/build_failure.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/build_failure.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/build_status.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/build_status.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/file_tree.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/file_tree.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/format.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/format.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/keybindings.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/keybindings.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/language_server.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/language_server.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/layout.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/layout.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/preserve.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/preserve.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/reflection.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/reflection.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/scene.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/scene.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/search.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/search.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/settings.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/settings.rs")
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/syntax.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/syntax.rs")
+            },
             FileTemplate {
                 relative_path: PathBuf::from("src/terminal.rs"),
                 contents: include_bytes!("../../bevy_project_template/src/terminal.rs")
             },
+            FileTemplate {
+                relative_path: PathBuf::from("src/toast.rs"),
+                contents: include_bytes!("../../bevy_project_template/src/toast.rs")
+            },
             FileTemplate {
                 relative_path: PathBuf::from(".cargo/config.toml"),
                 contents: include_bytes!("../../.cargo/config.toml"),
@@ -64,6 +124,62 @@ impl Template<'_> {
         Template { file_templates }
     }
 
+    /// `bevy_editor_common`, which the template and the watcher depend on
+    /// through `path = "../bevy_editor_common"`.
+    pub fn editor_common() -> Template<'static> {
+        let file_templates = vec![
+            FileTemplate {
+                relative_path: PathBuf::from("Cargo.toml"),
+                contents: include_bytes!("../../bevy_editor_common/Cargo.toml"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/lib.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/lib.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/build.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/build.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/config.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/config.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/egui_view.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/egui_view.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/failure.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/failure.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/log.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/log.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/lsp.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/lsp.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/process.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/process.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/protocol.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/protocol.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("src/vt.rs"),
+                contents: include_bytes!("../../bevy_editor_common/src/vt.rs"),
+            },
+            FileTemplate {
+                relative_path: PathBuf::from("benches/terminal.rs"),
+                contents: include_bytes!("../../bevy_editor_common/benches/terminal.rs"),
+            },
+        ];
+        Template { file_templates }
+    }
+
     pub fn build_template(&self, path: PathBuf) -> io::Result<()> {
         for template in &self.file_templates {
             let mut path_buf = path.clone();
@@ -87,3 +203,17 @@ pub struct FileTemplate<'a> {
     pub relative_path: PathBuf,
     pub contents: &'a [u8],
 }
+
+/// Gives a new project its own copy of `bevy_editor_common`, so it keeps
+/// building against the version it was made with whatever the manager
+/// updates to later.
+pub fn vendor_editor_common(project_dir: &Path) -> io::Result<()> {
+    Template::editor_common().build_template(project_dir.join("bevy_editor_common"))?;
+    let manifest = project_dir.join("Cargo.toml");
+    let contents = std::fs::read_to_string(&manifest)?;
+    let contents = contents.replace(
+        r#"path = "../bevy_editor_common""#,
+        r#"path = "bevy_editor_common""#,
+    );
+    std::fs::write(manifest, contents)
+}
@@ -0,0 +1,179 @@
+use crate::syntax::Language;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// How many undo steps each buffer keeps.
+const HISTORY_LEN: usize = 100;
+/// Edits closer together than this, in seconds, undo as one step.
+const GROUP_EDITS: f64 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DiskState {
+    InSync,
+    /// Another tool changed the file while we had unsaved edits.
+    Changed,
+    Deleted,
+}
+
+/// An open file: what's in the editor and what we last read or wrote to disk.
+pub struct Buffer {
+    pub path: PathBuf,
+    pub text: String,
+    saved: String,
+    pub disk: DiskState,
+    pub language: Language,
+    /// The file isn't UTF-8, `text` is a lossy copy we must never write back.
+    pub read_only: bool,
+    /// Chars to select and scroll to the next time it's drawn.
+    pub jump_to: Option<Range<usize>>,
+    history: History,
+}
+
+/// Whole text snapshots, the files we edit are small enough.
+struct History {
+    undo: Vec<String>,
+    redo: Vec<String>,
+    /// The text as of the last recorded edit.
+    last: String,
+    last_edit: f64,
+}
+
+impl Buffer {
+    pub fn open(path: PathBuf) -> Result<Buffer, String> {
+        let (text, read_only) =
+            read(&path).map_err(|err| format!("couldn't open {}: {err}", path.display()))?;
+        Ok(Buffer {
+            language: Language::from_path(&path),
+            path,
+            saved: text.clone(),
+            disk: DiskState::InSync,
+            read_only,
+            jump_to: None,
+            history: History {
+                undo: Vec::new(),
+                redo: Vec::new(),
+                last: text.clone(),
+                last_edit: f64::NEG_INFINITY,
+            },
+            text,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.disk == DiskState::Deleted || self.text != self.saved
+    }
+
+    pub fn save(&mut self) -> Result<(), String> {
+        if self.read_only {
+            return Err(format!("{} isn't UTF-8 text, it can't be saved from here", self.name()));
+        }
+        std::fs::write(&self.path, &self.text)
+            .map_err(|err| format!("couldn't save {}: {err}", self.path.display()))?;
+        self.saved = self.text.clone();
+        self.disk = DiskState::InSync;
+        Ok(())
+    }
+
+    /// Throws away the edits and takes whatever is on disk now.
+    pub fn revert(&mut self) -> Result<(), String> {
+        let (text, read_only) = read(&self.path)
+            .map_err(|err| format!("couldn't reload {}: {err}", self.path.display()))?;
+        self.read_only = read_only;
+        self.saved = text.clone();
+        self.text = text;
+        self.disk = DiskState::InSync;
+        Ok(())
+    }
+
+    /// Turns whatever changed `text` since the last call into an undo step,
+    /// called once a frame with egui's time.
+    pub fn record_edits(&mut self, time: f64) {
+        let history = &mut self.history;
+        if self.text == history.last {
+            return;
+        }
+        let previous = std::mem::replace(&mut history.last, self.text.clone());
+        if time - history.last_edit > GROUP_EDITS || history.undo.is_empty() {
+            history.undo.push(previous);
+            if history.undo.len() > HISTORY_LEN {
+                history.undo.remove(0);
+            }
+        }
+        history.last_edit = time;
+        history.redo.clear();
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(text) = self.history.undo.pop() {
+            let current = self.restore(text);
+            self.history.redo.push(current);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(text) = self.history.redo.pop() {
+            let current = self.restore(text);
+            self.history.undo.push(current);
+        }
+    }
+
+    /// Swaps in `text` from the history and puts the cursor where it differs.
+    fn restore(&mut self, text: String) -> String {
+        let changed_at = self
+            .text
+            .chars()
+            .zip(text.chars())
+            .take_while(|(a, b)| a == b)
+            .count();
+        self.jump_to = Some(changed_at..changed_at);
+        self.history.last = text.clone();
+        // the next edit is a step of its own
+        self.history.last_edit = f64::NEG_INFINITY;
+        std::mem::replace(&mut self.text, text)
+    }
+
+    /// Called when the file changed on disk. Our own saves are recognized and
+    /// ignored, clean buffers just follow the file, dirty ones get flagged.
+    pub fn disk_changed(&mut self) {
+        let (text, read_only) = match read(&self.path) {
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                self.disk = DiskState::Deleted;
+                return;
+            }
+            // probably mid-write, we'll hear about it again
+            Err(_) => return,
+        };
+        if text == self.saved {
+            if self.disk == DiskState::Deleted {
+                self.disk = DiskState::InSync;
+            }
+            return;
+        }
+        if self.text != self.saved {
+            self.disk = DiskState::Changed;
+            return;
+        }
+        self.read_only = read_only;
+        self.saved = text.clone();
+        self.text = text;
+        self.disk = DiskState::InSync;
+    }
+}
+
+/// The file's text and whether it had to be decoded lossily.
+fn read(path: &Path) -> io::Result<(String, bool)> {
+    let bytes = std::fs::read(path)?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => (text, false),
+        Err(err) => (String::from_utf8_lossy(err.as_bytes()).into_owned(), true),
+    })
+}
@@ -0,0 +1,154 @@
+use crate::code_editor::CodeEditor;
+use crate::terminal::{ConnectionStatus, WatcherConnection};
+use bevy::prelude::World;
+use bevy_editor_common::egui_26::TerminalView;
+use bevy_editor_common::failure::{CompileError, Failure, Location, Panic};
+use bevy_editor_common::protocol::{FailureReport, WatcherCommand, FAILURE_REPORT};
+use bevy_editor_common::vt;
+use bevy_editor_pls::egui::{Button, CollapsingHeader, RichText, ScrollArea, Ui};
+use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
+
+/// Shown in `ONLY_UI` mode: why the last run went down, from the report the
+/// watcher left us in [`FAILURE_REPORT`].
+pub struct BuildFailure;
+
+#[derive(Default)]
+pub struct BuildFailureState {
+    report: Option<Result<LoadedReport, String>>,
+}
+
+struct LoadedReport {
+    status: String,
+    failure: Failure,
+    output: vt::Terminal,
+    output_view: TerminalView,
+}
+
+fn load_report() -> Option<Result<LoadedReport, String>> {
+    let path = std::env::var(FAILURE_REPORT).ok()?;
+    let report = std::fs::File::open(&path)
+        .and_then(|mut file| FailureReport::read_from(&mut file))
+        .map_err(|err| format!("couldn't read the failure report at {path}: {err}"));
+    Some(report.map(|report| {
+        let mut output = vt::Terminal::default();
+        output.advance(&report.output);
+        LoadedReport {
+            status: report.status,
+            failure: Failure::parse(&report.output),
+            output,
+            output_view: TerminalView::default(),
+        }
+    }))
+}
+
+impl EditorWindow for BuildFailure {
+    type State = BuildFailureState;
+    const NAME: &'static str = "Build/Run Failure";
+
+    fn ui(world: &mut World, mut cx: EditorWindowContext, ui: &mut Ui) {
+        let state = cx.state_mut::<BuildFailure>().unwrap();
+        let connection = world.resource::<WatcherConnection>();
+
+        ui.horizontal(|ui| {
+            let connected = connection.status() == ConnectionStatus::Connected;
+            if ui
+                .add_enabled(connected && !connection.is_exiting(), Button::new("Retry"))
+                .on_hover_text("run the game again, with its systems")
+                .clicked()
+            {
+                connection.command(WatcherCommand::Restart);
+            }
+            if !connected {
+                ui.label("(needs the watcher)");
+            }
+        });
+        ui.separator();
+
+        if state.report.is_none() {
+            state.report = load_report();
+        }
+        let report = match &mut state.report {
+            None => {
+                ui.label("Nothing failed, the game was started in ONLY_UI mode on purpose.");
+                return;
+            }
+            Some(Err(err)) => {
+                ui.colored_label(ui.visuals().error_fg_color, err.as_str());
+                return;
+            }
+            Some(Ok(report)) => report,
+        };
+
+        ui.heading(format!("The last run failed ({})", report.status));
+        let mut open = None;
+        ScrollArea::vertical()
+            .id_source("build failure")
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                for error in &report.failure.errors {
+                    if let Some(location) = compile_error_ui(error, ui) {
+                        open = Some(location);
+                    }
+                }
+                if let Some(panic) = &report.failure.panic {
+                    if let Some(location) = panic_ui(panic, ui) {
+                        open = Some(location);
+                    }
+                }
+                if report.failure.is_empty() {
+                    ui.label("Couldn't find a compile error or a panic, see the full output below.");
+                }
+                CollapsingHeader::new("Full output")
+                    .default_open(report.failure.is_empty())
+                    .show(ui, |ui| {
+                        ui.set_height(400.0);
+                        report.output_view.rows(&report.output, ui);
+                    });
+            });
+        let open = open.or_else(|| report.output_view.clicked_location());
+        if let (Some(location), Some(code)) = (open, cx.state_mut::<CodeEditor>()) {
+            code.open_location(&location);
+        }
+    }
+}
+
+/// Returns the location if it was clicked.
+fn location_ui(location: &Location, ui: &mut Ui) -> Option<Location> {
+    ui.link(RichText::new(location.to_string()).monospace())
+        .on_hover_text("open in the code editor")
+        .clicked()
+        .then(|| location.clone())
+}
+
+fn compile_error_ui(error: &CompileError, ui: &mut Ui) -> Option<Location> {
+    let title = match &error.code {
+        Some(code) => format!("error[{code}]: {}", error.message),
+        None => format!("error: {}", error.message),
+    };
+    ui.label(RichText::new(title).color(ui.visuals().error_fg_color).strong());
+    let clicked = error.location.as_ref().and_then(|location| location_ui(location, ui));
+    if !error.details.is_empty() {
+        ui.monospace(error.details.join("\n"));
+    }
+    ui.separator();
+    clicked
+}
+
+fn panic_ui(panic: &Panic, ui: &mut Ui) -> Option<Location> {
+    ui.label(
+        RichText::new(format!("thread '{}' panicked", panic.thread))
+            .color(ui.visuals().error_fg_color)
+            .strong(),
+    );
+    let clicked = panic.location.as_ref().and_then(|location| location_ui(location, ui));
+    ui.monospace(&panic.message);
+    if panic.backtrace.is_empty() {
+        ui.label("Run with RUST_BACKTRACE=1 for a backtrace.");
+    } else {
+        CollapsingHeader::new("Backtrace").show(ui, |ui| {
+            ui.monospace(panic.backtrace.join("\n"));
+        });
+    }
+    ui.separator();
+    clicked
+}
@@ -0,0 +1,204 @@
+use crate::terminal::WatcherConnection;
+use bevy::prelude::{Local, Res, ResMut, Resource};
+use bevy_editor_common::protocol::BuildEvent;
+use bevy_editor_pls::egui::{Color32, RichText, Ui};
+use std::time::{Duration, SystemTime};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildPhase {
+    /// Nothing heard from the watcher yet.
+    Unknown,
+    Building,
+    Succeeded,
+    Failed,
+    /// The last build made it into this game. Inferred here rather than
+    /// reported, see [`BuildStatus`].
+    Reloaded,
+}
+
+/// Where the last save is on its way into the running game: building, built,
+/// failed or reloaded, with how long the build took.
+///
+/// The watcher only sees cargo, the library is swapped in by the game itself,
+/// so reloads are worked out on this side: every load after the first is a
+/// reload, and it belongs to the build that started before it. Both times come
+/// from this machine's clock, the watcher's through [`BuildEvent`]s.
+#[derive(Resource)]
+pub struct BuildStatus {
+    pub phase: BuildPhase,
+    started: Option<SystemTime>,
+    took: Option<Duration>,
+    finished: Option<SystemTime>,
+    reloaded: Option<SystemTime>,
+    /// Builds that finished before this are what we're running.
+    process_started: SystemTime,
+    loads: usize,
+}
+
+impl Default for BuildStatus {
+    fn default() -> Self {
+        BuildStatus {
+            phase: BuildPhase::Unknown,
+            started: None,
+            took: None,
+            finished: None,
+            reloaded: None,
+            process_started: SystemTime::now(),
+            loads: 0,
+        }
+    }
+}
+
+impl BuildStatus {
+    fn event(&mut self, event: BuildEvent, at: SystemTime) {
+        match event {
+            BuildEvent::Started => {
+                self.phase = BuildPhase::Building;
+                self.started = Some(at);
+                self.took = None;
+                self.finished = None;
+            }
+            BuildEvent::Succeeded | BuildEvent::Failed => {
+                self.took = self
+                    .started
+                    .and_then(|started| at.duration_since(started).ok());
+                self.finished = Some(at);
+                self.phase = match event {
+                    BuildEvent::Failed => BuildPhase::Failed,
+                    // the library can get swapped in before we hear the build is done
+                    _ if self.reloaded.zip(self.started).is_some_and(|(reloaded, started)| reloaded > started) => {
+                        BuildPhase::Reloaded
+                    }
+                    _ => BuildPhase::Succeeded,
+                };
+            }
+        }
+    }
+
+    fn loaded(&mut self, at: SystemTime) {
+        self.loads += 1;
+        // the first load is just the game starting
+        if self.loads > 1 {
+            self.reloaded = Some(at);
+            if self.phase == BuildPhase::Succeeded {
+                self.phase = BuildPhase::Reloaded;
+            }
+        }
+    }
+
+    /// How long the build has been going, or took.
+    pub fn elapsed(&self) -> Option<Duration> {
+        match self.phase {
+            BuildPhase::Building => self.started.and_then(|started| started.elapsed().ok()),
+            _ => self.took,
+        }
+    }
+}
+
+pub fn track_builds(connection: Res<WatcherConnection>, mut status: ResMut<BuildStatus>) {
+    for (event, at) in connection.builds().try_iter() {
+        status.event(event, at);
+    }
+}
+
+/// Has to be added to the reloadable part of the app: the new library brings
+/// a new copy of the system, with a fresh `Local`, every time it's loaded.
+pub fn count_loads(mut status: ResMut<BuildStatus>, mut counted: Local<bool>) {
+    if !*counted {
+        *counted = true;
+        status.loaded(SystemTime::now());
+    }
+}
+
+fn ago(time: SystemTime) -> String {
+    let seconds = time.elapsed().unwrap_or_default().as_secs();
+    match seconds {
+        0..=4 => "just now".to_string(),
+        5..=59 => format!("{seconds}s ago"),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        _ => format!("{}h ago", seconds / 3600),
+    }
+}
+
+pub fn status_bar(ui: &mut Ui, status: &BuildStatus) {
+    let took = status
+        .elapsed()
+        .map_or(String::new(), |elapsed| format!("{:.1}s", elapsed.as_secs_f32()));
+    ui.horizontal(|ui| match status.phase {
+        BuildPhase::Unknown => {}
+        BuildPhase::Building => {
+            ui.spinner();
+            ui.label(format!("Building... {took}"));
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+        BuildPhase::Succeeded if status.finished.is_some_and(|finished| finished < status.process_started) => {
+            ui.label(format!("Running the latest build (built in {took})"));
+        }
+        BuildPhase::Succeeded => {
+            ui.spinner();
+            ui.label(format!("Built in {took}, waiting for the game to reload..."));
+        }
+        BuildPhase::Failed => {
+            ui.label(
+                RichText::new(format!("Build failed after {took}, the game still runs the previous build"))
+                    .color(ui.visuals().error_fg_color),
+            )
+            .on_hover_text("the errors are in the terminal");
+        }
+        BuildPhase::Reloaded => {
+            let when = status.reloaded.map_or(String::new(), ago);
+            ui.label(
+                RichText::new(format!("Reloaded {when} (built in {took})")).color(Color32::from_rgb(120, 200, 120)),
+            );
+            // keep "ago" current
+            ui.ctx().request_repaint_after(Duration::from_secs(1));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    enum Step {
+        Build(BuildEvent, u64),
+        Load(u64),
+    }
+    use Step::{Build, Load};
+
+    fn phase(steps: &[Step]) -> BuildPhase {
+        let mut status = BuildStatus::default();
+        let at = |seconds| SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+        for step in steps {
+            match *step {
+                Build(event, seconds) => status.event(event, at(seconds)),
+                Load(seconds) => status.loaded(at(seconds)),
+            }
+        }
+        status.phase
+    }
+
+    #[test]
+    fn reloads_are_inferred_from_loads() {
+        use BuildEvent::{Failed, Started, Succeeded};
+        let cases = [
+            (vec![], BuildPhase::Unknown),
+            (vec![Load(0), Build(Started, 1)], BuildPhase::Building),
+            (vec![Load(0), Build(Started, 1), Build(Succeeded, 2)], BuildPhase::Succeeded),
+            (vec![Load(0), Build(Started, 1), Build(Succeeded, 2), Load(3)], BuildPhase::Reloaded),
+            // the new library can be swapped in before cargo says it's done
+            (vec![Load(0), Build(Started, 1), Load(2), Build(Succeeded, 3)], BuildPhase::Reloaded),
+            // a reload from the build before doesn't count for this one
+            (
+                vec![Load(0), Build(Started, 1), Build(Succeeded, 2), Load(3), Build(Started, 4), Build(Succeeded, 5)],
+                BuildPhase::Succeeded,
+            ),
+            // the first load is the game starting, not a reload
+            (vec![Build(Started, 1), Build(Succeeded, 2), Load(3)], BuildPhase::Succeeded),
+            (vec![Load(0), Build(Started, 1), Build(Failed, 2), Load(3)], BuildPhase::Failed),
+        ];
+        for (steps, expected) in cases {
+            assert_eq!(phase(&steps), expected);
+        }
+    }
+}
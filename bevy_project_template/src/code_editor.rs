@@ -1,138 +1,501 @@
-use std::fs::DirEntry;
 use std::path::PathBuf;
 use bevy::prelude::World;
+use bevy_editor_pls::controls::EditorControls;
+use bevy_editor_common::failure::Location;
+use bevy_editor_common::lsp::Position;
 use bevy_editor_pls::egui;
-use bevy_editor_pls::egui::{Align, Layout, RichText, Ui};
+use bevy_editor_pls::egui::epaint::text::cursor::CCursor;
+use bevy_editor_pls::egui::text::CCursorRange;
+use bevy_editor_pls::egui::text_edit::{TextEditOutput, TextEditState};
+use bevy_editor_pls::egui::{Align, RichText, Ui};
 use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
-use egui_code_editor::{ColorTheme, Syntax};
+use crate::buffer::{Buffer, DiskState};
+use crate::build_status::{self, BuildStatus};
+use crate::file_tree::{FileTree, FileTreeEvent};
+use crate::format;
+use crate::keybindings::{self, EditorAction, Keybindings};
+use crate::language_server::{self, LanguageServer};
+use crate::search::{FindBar, FindInFiles};
+use crate::settings::EditorSettings;
+use crate::syntax::{self, Language};
+use crate::toast::Toasts;
 
+#[derive(Default)]
 pub struct CodeEditor;
 
 pub struct CodeEditorState {
-    code: String,
-    selected_file: Option<PathBuf>,
+    buffers: Vec<Buffer>,
+    active: Option<usize>,
+    /// A buffer with unsaved changes we've been asked to close.
+    closing: Option<usize>,
+    file_tree: FileTree,
+    settings: EditorSettings,
+    language_server: LanguageServer,
+    find: FindBar,
+    find_in_files: FindInFiles,
+    /// Waiting for a key press to bind to this.
+    rebinding: Option<EditorAction>,
+    /// Whether the text had the keyboard last frame.
+    editor_focused: bool,
+    toasts: Toasts,
+    error: Option<String>,
 }
 
-impl Default for CodeEditor {
-    fn default() -> Self {
-        Self {}
-    }
-}
 impl Default for CodeEditorState {
     fn default() -> Self {
         Self {
-            code: "".to_string(),
-            selected_file: None,
+            buffers: Vec::new(),
+            active: None,
+            closing: None,
+            file_tree: FileTree::default(),
+            settings: EditorSettings::load(),
+            language_server: LanguageServer::default(),
+            find: FindBar::default(),
+            find_in_files: FindInFiles::default(),
+            rebinding: None,
+            editor_focused: false,
+            toasts: Toasts::default(),
+            error: None,
         }
     }
 }
 
-impl EditorWindow for CodeEditor {
-    type State = CodeEditorState;
-    const NAME: &'static str = "Code Editor";
+impl CodeEditorState {
+    fn report(&mut self, result: Result<(), String>) {
+        if let Err(err) = result {
+            self.error = Some(err);
+        }
+    }
 
-    fn ui(world: &mut World, mut cx: EditorWindowContext, ui: &mut Ui) {
-        let code = cx.state_mut::<CodeEditor>().unwrap();
+    fn active_path(&self) -> Option<PathBuf> {
+        self.active.map(|active| self.buffers[active].path.clone())
+    }
 
-        ui.horizontal_top(|ui| {
-           let mut path_buf = None;
-            ui.vertical(|ui| {
-               let dir = std::fs::read_dir(std::env::current_dir().unwrap()).unwrap();
-                for dir in dir.into_iter() {
-                    if let Ok(dir) = dir {
-                        if let Some(dir) = display_dir(ui, dir) {
-                            path_buf.replace(dir);
-                        }
+    /// Formats the buffer in place, a failure is only worth a notification.
+    fn format(&mut self, index: usize) {
+        let buffer = &mut self.buffers[index];
+        if buffer.read_only {
+            return;
+        }
+        match format::format(buffer.language, &buffer.path, &buffer.text) {
+            Ok(text) if text != buffer.text => buffer.text = text,
+            Ok(_) => {}
+            Err(err) => self.toasts.error(err),
+        }
+    }
+
+    fn save(&mut self, index: usize) {
+        if self.settings.format_on_save && format::can_format(self.buffers[index].language) {
+            // a file that doesn't parse yet still gets saved as is
+            self.format(index);
+        }
+        let result = self.buffers[index].save();
+        if result.is_ok() {
+            self.language_server.saved(&self.buffers[index].path);
+        }
+        self.report(result);
+    }
+
+    fn save_all(&mut self) {
+        for index in 0..self.buffers.len() {
+            if self.buffers[index].is_dirty() {
+                self.save(index);
+            }
+        }
+    }
+
+    fn open(&mut self, path: PathBuf) {
+        if let Some(index) = self.buffers.iter().position(|buffer| buffer.path == path) {
+            self.active = Some(index);
+            return;
+        }
+        match Buffer::open(path) {
+            Ok(buffer) => {
+                self.buffers.push(buffer);
+                self.active = Some(self.buffers.len() - 1);
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    /// Opens `path` with the cursor on `position`.
+    fn open_at(&mut self, path: PathBuf, position: Position) {
+        self.open(path);
+        if let Some(active) = self.active {
+            let buffer = &mut self.buffers[active];
+            let index = position.to_char_index(&buffer.text);
+            buffer.jump_to = Some(index..index);
+        }
+    }
+
+    /// Opens a `file:line:column` the game or its build printed, relative paths
+    /// are from the project root.
+    pub fn open_location(&mut self, location: &Location) {
+        let path = self.file_tree.root().join(&location.file);
+        // rustc counts from 1
+        let position = Position {
+            line: location.line.saturating_sub(1) as u32,
+            character: location.column.unwrap_or(1).saturating_sub(1) as u32,
+        };
+        self.open_at(path, position);
+    }
+
+    /// Asks first if there are unsaved changes.
+    fn close(&mut self, index: usize) {
+        if self.buffers[index].is_dirty() {
+            self.closing = Some(index);
+            return;
+        }
+        self.force_close(index);
+    }
+
+    fn force_close(&mut self, index: usize) {
+        self.buffers.remove(index);
+        self.closing = None;
+        self.active = match self.active {
+            _ if self.buffers.is_empty() => None,
+            Some(active) if active > index => Some(active - 1),
+            Some(active) => Some(active.min(self.buffers.len() - 1)),
+            None => None,
+        };
+    }
+
+    fn file_tree_event(&mut self, event: FileTreeEvent) {
+        match event {
+            FileTreeEvent::Open(path) => self.open(path),
+            FileTreeEvent::Renamed { from, to } => {
+                for buffer in self.buffers.iter_mut() {
+                    if let Ok(inside) = buffer.path.strip_prefix(&from) {
+                        buffer.path = to.join(inside);
                     }
                 }
-            });
-            if let Some(buf) = path_buf {
-                if let Some(old) = code.selected_file.clone() {
-                    std::fs::write(old, &code.code).unwrap();
-                }
-                code.selected_file.replace(buf.clone());
-                let reader = std::fs::read(buf).unwrap();
-                code.code = String::from_utf8(reader).unwrap();
-            }
-            let mut control_key = false;
-            ui.input(|input| {
-                for event in &input.events {
-                    match event {
-                        egui::Event::Key{key, physical_key, pressed, repeat, modifiers } => {
-                            if modifiers.ctrl && key.eq(&egui::Key::S) {
-                                if let Some(old) = code.selected_file.clone() {
-                                    std::fs::write(old, &code.code).unwrap();
-                                }
-                            }
-                            if modifiers.ctrl {
-                                control_key = true;
-                            }
-                        },
-                        _ => {}
+            }
+            // deleting from the tree is explicit, anything else goes through `Changed`
+            FileTreeEvent::Deleted(path) => {
+                while let Some(index) = self.buffers.iter().position(|buffer| buffer.path.starts_with(&path)) {
+                    self.force_close(index);
+                }
+            }
+            FileTreeEvent::Changed(path) => {
+                for buffer in self.buffers.iter_mut().filter(|buffer| buffer.path == path) {
+                    buffer.disk_changed();
+                }
+            }
+        }
+    }
+
+    fn run(&mut self, action: EditorAction, active: usize) {
+        match action {
+            EditorAction::Save => self.save(active),
+            EditorAction::SaveAll => self.save_all(),
+            EditorAction::CloseTab => self.close(active),
+            EditorAction::Find => self.find.show(),
+            EditorAction::Format => self.format(active),
+            EditorAction::Undo => self.buffers[active].undo(),
+            EditorAction::Redo => self.buffers[active].redo(),
+            // these need the editor's cursor, the language server handles them
+            EditorAction::GoToDefinition | EditorAction::Complete => {}
+        }
+    }
+
+    fn keybindings_ui(&mut self, ui: &mut Ui, keybindings: &Keybindings) {
+        if let Some(action) = self.rebinding {
+            if let Some(binding) = keybindings::capture(ui) {
+                self.rebinding = None;
+                if binding.key != egui::Key::Escape {
+                    self.settings.keybindings.insert(action, binding);
+                    let result = self.settings.save();
+                    self.report(result);
+                }
+            }
+        }
+        egui::CollapsingHeader::new("Keybindings")
+            .id_source("code editor keybindings")
+            .show(ui, |ui| {
+                for (action, binding, taken_by) in keybindings.conflicts() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("{binding} ({}) is bevy_editor_pls' {taken_by}, it's ignored here", action.name()),
+                    );
+                }
+                let mut changed = false;
+                egui::Grid::new("code editor keybindings grid").show(ui, |ui| {
+                    for action in EditorAction::ALL {
+                        ui.label(action.name());
+                        let text = if self.rebinding == Some(action) {
+                            "press a key (Escape cancels)".to_string()
+                        } else {
+                            keybindings
+                                .binding(action)
+                                .map_or("none".to_string(), |binding| binding.to_string())
+                        };
+                        if ui.button(text).on_hover_text("click to change").clicked() {
+                            self.rebinding = Some(action);
+                        }
+                        let overridden = self.settings.keybindings.contains_key(&action);
+                        if ui.add_enabled(overridden, egui::Button::new("default")).clicked() {
+                            self.settings.keybindings.remove(&action);
+                            changed = true;
+                        }
+                        ui.end_row();
                     }
+                });
+                if changed {
+                    let result = self.settings.save();
+                    self.report(result);
                 }
             });
+    }
 
-            if let Some(_) = code.selected_file.clone() {
-                if ui.button("close").clicked() {
-                    if let Some(old) = code.selected_file.clone() {
-                        std::fs::write(old, &code.code).unwrap();
-                    }
-                    code.selected_file.take();
-                }
-                match control_key {
-                    true => {
-                        let mut temp = code.code.clone();
-                        egui_code_editor::CodeEditor::default()
-                            .id_source("code editor")
-                            .with_rows(12)
-                            .with_fontsize(14.0)
-                            .with_theme(ColorTheme::GRUVBOX)
-                            .with_syntax(Syntax::rust())
-                            .with_numlines(true)
-                            .show(ui, &mut temp);
-                    },
-                    false => {
-                        egui_code_editor::CodeEditor::default()
-                            .id_source("code editor")
-                            .with_rows(12)
-                            .with_fontsize(14.0)
-                            .with_theme(ColorTheme::GRUVBOX)
-                            .with_syntax(Syntax::rust())
-                            .with_numlines(true)
-                            .show(ui, &mut code.code);
-                    }
+    fn tabs_ui(&mut self, ui: &mut Ui) {
+        let mut close = None;
+        ui.horizontal_wrapped(|ui| {
+            for (index, buffer) in self.buffers.iter().enumerate() {
+                let mut name = buffer.name();
+                if buffer.is_dirty() {
+                    name.push_str(" \u{25CF}");
                 }
+                let tab = ui
+                    .selectable_label(self.active == Some(index), name)
+                    .on_hover_text(buffer.path.display().to_string());
+                if tab.clicked() {
+                    self.active = Some(index);
+                }
+                if tab.middle_clicked() || ui.small_button(egui_phosphor::regular::X).clicked() {
+                    close = Some(index);
+                }
+                ui.separator();
             }
         });
+        if let Some(index) = close {
+            self.close(index);
+        }
     }
-}
 
-fn display_dir(ui: &mut Ui, dir: DirEntry) -> Option<PathBuf> {
-    if dir.file_type().unwrap().is_dir() {
-        let mut path_buf = None;
-        ui.collapsing(RichText::new(format!("{} {}", egui_phosphor::regular::FOLDER, dir.file_name().to_str().unwrap())), |ui| {
-            for dir in  std::fs::read_dir(dir.path()).unwrap().into_iter() {
-                if let Ok(dir) = dir {
-                    if let Some(buf) = display_dir(ui, dir) {
-                        path_buf.replace(buf);
+    fn toolbar_ui(&mut self, ui: &mut Ui, active: usize) {
+        ui.horizontal(|ui| {
+            if ui.button("save").clicked() {
+                self.save(active);
+            }
+            let any_dirty = self.buffers.iter().any(Buffer::is_dirty);
+            if ui.add_enabled(any_dirty, egui::Button::new("save all")).clicked() {
+                self.save_all();
+            }
+            if ui
+                .add_enabled(self.buffers[active].is_dirty(), egui::Button::new("revert"))
+                .on_hover_text("throw away unsaved changes")
+                .clicked()
+            {
+                let result = self.buffers[active].revert();
+                self.report(result);
+            }
+            let language = self.buffers[active].language;
+            if ui
+                .add_enabled(format::can_format(language), egui::Button::new("format"))
+                .on_hover_text("format document")
+                .clicked()
+            {
+                self.format(active);
+            }
+            if ui.checkbox(&mut self.settings.format_on_save, "format on save").changed() {
+                let result = self.settings.save();
+                self.report(result);
+            }
+            if ui.button("find").on_hover_text("find and replace in this file").clicked() {
+                self.find.show();
+            }
+            if ui.button("reveal").on_hover_text("show in the file tree").clicked() {
+                let path = self.buffers[active].path.clone();
+                self.file_tree.reveal(&path);
+            }
+
+            ui.separator();
+            let language = &mut self.buffers[active].language;
+            egui::ComboBox::from_id_source("code editor language")
+                .selected_text(language.name())
+                .show_ui(ui, |ui| {
+                    for option in Language::ALL {
+                        ui.selectable_value(language, option, option.name());
+                    }
+                });
+            let mut theme_changed = false;
+            egui::ComboBox::from_id_source("code editor theme")
+                .selected_text(self.settings.theme.clone())
+                .show_ui(ui, |ui| {
+                    for (name, _) in syntax::THEMES {
+                        theme_changed |= ui
+                            .selectable_value(&mut self.settings.theme, name.to_string(), name)
+                            .changed();
                     }
+                });
+            if theme_changed {
+                let result = self.settings.save();
+                self.report(result);
+            }
+        });
+    }
+
+    fn confirm_close_ui(&mut self, ui: &mut Ui, index: usize) {
+        ui.horizontal(|ui| {
+            ui.label(format!("{} has unsaved changes.", self.buffers[index].name()));
+            if ui.button("Save").clicked() {
+                self.save(index);
+                if !self.buffers[index].is_dirty() {
+                    self.force_close(index);
                 }
             }
+            if ui.button("Discard").clicked() {
+                self.force_close(index);
+            }
+            if ui.button("Cancel").clicked() {
+                self.closing = None;
+            }
         });
-        path_buf
-    } else {
+    }
 
-        let icon = if dir.file_name().to_str().unwrap().ends_with(".rs") {
-            egui_phosphor::regular::FILE_RS
-        } else {
-            egui_phosphor::regular::FILE
+    fn disk_state_ui(&mut self, ui: &mut Ui, active: usize) {
+        let message = match self.buffers[active].disk {
+            DiskState::InSync => return,
+            DiskState::Changed => "The file changed on disk.",
+            DiskState::Deleted => "The file was deleted on disk.",
         };
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(message).color(ui.visuals().warn_fg_color));
+            if self.buffers[active].disk == DiskState::Changed && ui.button("Reload").clicked() {
+                let result = self.buffers[active].revert();
+                self.report(result);
+            }
+            if ui.button("Keep mine").on_hover_text("overwrite it with the editor's version").clicked() {
+                self.save(active);
+            }
+        });
+    }
+}
 
-        if ui.button(egui::RichText::new(format!("{} {}", icon, dir.file_name().to_str().unwrap()))).clicked() {
-            Some(dir.path())
-        } else {
-            None
+impl EditorWindow for CodeEditor {
+    type State = CodeEditorState;
+    const NAME: &'static str = "Code Editor";
+
+    fn ui(world: &mut World, mut cx: EditorWindowContext, ui: &mut Ui) {
+        let code = cx.state_mut::<CodeEditor>().unwrap();
+
+        if let Some(err) = code.error.clone() {
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+                if ui.small_button(egui_phosphor::regular::X).clicked() {
+                    code.error = None;
+                }
+            });
         }
+
+        ui.horizontal_top(|ui| {
+            let mut events = Vec::new();
+            ui.vertical(|ui| {
+                let selected = code.active_path();
+                events = code.file_tree.show(ui, selected.as_deref());
+            });
+            for event in events {
+                code.file_tree_event(event);
+            }
+            let root = code.file_tree.root().to_path_buf();
+            code.language_server.sync(&code.buffers, &root);
+            if let Some((path, position)) = code.language_server.poll() {
+                code.open_at(path, position);
+            }
+            let keybindings = Keybindings::new(&code.settings.keybindings, world.get_resource::<EditorControls>());
+            let keyboard_here = code.editor_focused || ui.rect_contains_pointer(ui.max_rect());
+            let actions = if keyboard_here && code.rebinding.is_none() {
+                keybindings.take_actions(ui)
+            } else {
+                Vec::new()
+            };
+
+            ui.vertical(|ui| {
+                code.tabs_ui(ui);
+                if let Some(err) = code.language_server.error() {
+                    ui.weak(err);
+                }
+                if let Some((path, position)) = language_server::problems_ui(&code.language_server, ui, &root) {
+                    code.open_at(path, position);
+                }
+                if let Some((path, position)) = code.find_in_files.ui(ui, &root) {
+                    code.open_at(path, position);
+                }
+                code.keybindings_ui(ui, &keybindings);
+                if let Some(index) = code.closing {
+                    code.confirm_close_ui(ui, index);
+                }
+                let Some(active) = code.active else {
+                    code.editor_focused = false;
+                    return;
+                };
+                for action in &actions {
+                    code.run(*action, active);
+                }
+                // the tab might just have been closed
+                let Some(active) = code.active else {
+                    code.editor_focused = false;
+                    return;
+                };
+                code.toolbar_ui(ui, active);
+                code.disk_state_ui(ui, active);
+                if code.buffers[active].read_only {
+                    ui.label(
+                        RichText::new("This file isn't UTF-8 text, it's shown read-only.")
+                            .color(ui.visuals().warn_fg_color),
+                    );
+                }
+                let theme = syntax::theme(&code.settings.theme);
+                let buffer = &mut code.buffers[active];
+                code.find.ui(ui, buffer);
+                let mut editor = egui_code_editor::CodeEditor::default()
+                    .id_source(format!("code editor {}", buffer.path.display()))
+                    .with_rows(12)
+                    .with_fontsize(14.0)
+                    .with_theme(theme)
+                    .with_syntax(buffer.language.syntax())
+                    .with_numlines(true);
+                // edits made through the copy are thrown away
+                let output = if buffer.read_only {
+                    let mut temp = buffer.text.clone();
+                    editor.show(ui, &mut temp)
+                } else {
+                    editor.show(ui, &mut buffer.text)
+                };
+                code.editor_focused = output.response.has_focus();
+                jump(ui, buffer, &output);
+                code.find.paint(ui, &buffer.text, &output);
+                code.language_server.editor_ui(ui, buffer, &output, &actions);
+                buffer.record_edits(ui.input(|input| input.time));
+            });
+        });
+        if let Some(status) = world.get_resource::<BuildStatus>() {
+            ui.separator();
+            build_status::status_bar(ui, status);
+        }
+        code.toasts.show(ui, "code editor toasts");
     }
-}
\ No newline at end of file
+}
+
+/// Selects `buffer.jump_to` and scrolls it into view.
+fn jump(ui: &Ui, buffer: &mut Buffer, output: &TextEditOutput) {
+    let Some(range) = buffer.jump_to.take() else {
+        return;
+    };
+    let id = output.response.id;
+    let mut state = TextEditState::load(ui.ctx(), id).unwrap_or_default();
+    state.cursor.set_char_range(Some(CCursorRange::two(
+        CCursor::new(range.start),
+        CCursor::new(range.end),
+    )));
+    state.store(ui.ctx(), id);
+    // unless something else, like the find bar, has the keyboard
+    if ui.memory(|memory| memory.focus().is_none()) {
+        output.response.request_focus();
+    }
+    let rect = output
+        .galley
+        .pos_from_ccursor(CCursor::new(range.start))
+        .translate(output.galley_pos.to_vec2());
+    ui.scroll_to_rect(rect, Some(Align::Center));
+}
@@ -1,14 +1,24 @@
-use crate::terminal;
-use crate::terminal::display_terminal;
-use bevy::app::{Plugin, Update};
-use bevy::prelude::{MonitorSelection, Window, WindowPosition, World};
-use bevy::window::{WindowRef, WindowResolution};
+use crate::build_failure::BuildFailure;
+use crate::build_status::{status_bar, track_builds, BuildStatus};
+use crate::terminal::{exit_when_flushed, ConnectionStatus, WatcherConnection};
+use bevy_editor_common::config::{EditorConfig, WindowPlacement};
+use bevy_editor_common::egui_26::{display_terminal, TerminalView};
+use bevy_editor_common::protocol::{EditorToWatcher, WatcherCommand};
+use bevy_editor_common::vt;
+use bevy::app::{App, Plugin, PostUpdate, Startup, Update};
+use bevy::prelude::{
+    Commands, Component, Entity, MonitorSelection, Query, Window, WindowPosition, With, World,
+};
+use bevy::window::{RawHandleWrapper, WindowMode, WindowRef, WindowResolution};
 use bevy_editor_pls::egui::{FontDefinitions, Ui};
-use bevy_editor_pls::{controls, egui_dock, EditorWindowPlacement};
+use bevy_editor_pls::{controls, EditorWindowPlacement};
 use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
 use bevy_editor_pls_core::{editor, AddEditorWindow};
-use crossbeam_channel::{Receiver, Sender};
 use crate::code_editor::CodeEditor;
+use crate::layout::{save_layout_on_exit, switch_layout, DockWindow, EditorLayout, LayoutWindow};
+use crate::preserve::{self, ResetOnReload};
+use crate::reflection::ReflectionWindow;
+use crate::scene::{load_startup_scene, open_pending_scene, ProjectScene, ResetMarker, ScenesWindow};
 
 pub struct Terminal;
 
@@ -18,49 +28,150 @@ pub fn add_to_fonts(ui: &mut Ui) {
     ui.ctx().set_fonts(fonts);
 }
 
+#[derive(Default)]
 pub struct TerminalState {
-    terminal_buf: String,
-    terminal_output: Receiver<u8>,
-    quit_reason: Sender<u8>,
-    auto_scroll: bool,
-}
-
-impl Default for TerminalState {
-    fn default() -> Self {
-        let (terminal_output, quit_reason) = terminal::setup_streams();
-        Self {
-            terminal_buf: "".to_string(),
-            terminal_output,
-            quit_reason,
-            auto_scroll: false,
-        }
-    }
+    terminal: vt::Terminal,
+    terminal_view: TerminalView,
 }
 
 impl EditorWindow for Terminal {
     type State = TerminalState;
-    const NAME: &'static str = "";
+    const NAME: &'static str = "Terminal";
 
     fn ui(world: &mut World, mut cx: EditorWindowContext, ui: &mut Ui) {
         let terminal_state = cx.state_mut::<Terminal>().unwrap();
+        let connection = world.resource::<WatcherConnection>();
 
-        let mut scroll_to_bottom = !terminal_state.terminal_output.is_empty();
-        if terminal_state.auto_scroll {
-            scroll_to_bottom = false;
+        let status = connection.status();
+        match &status {
+            ConnectionStatus::Standalone => {
+                ui.label("Not connected to the watcher, run the project from the project manager to see its output here.");
+                return;
+            }
+            ConnectionStatus::Connecting => {
+                ui.label("Connecting to the watcher...");
+            }
+            ConnectionStatus::Connected => {}
+            ConnectionStatus::Disconnected(reason) => {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("Lost the watcher ({reason}), reconnecting..."),
+                );
+            }
         }
-        ui.checkbox(&mut terminal_state.auto_scroll, "auto scroll terminal");
-        display_terminal(
-            &mut terminal_state.terminal_buf,
-            terminal_state.terminal_output.clone(),
+        status_bar(ui, world.resource::<BuildStatus>());
+
+        ui.add_enabled_ui(status == ConnectionStatus::Connected && !connection.is_exiting(), |ui| {
+            ui.horizontal(|ui| {
+                for (command, label, hover) in [
+                    (WatcherCommand::Rebuild, "Rebuild", "rebuild and hot reload without restarting"),
+                    (WatcherCommand::Restart, "Restart game", "exit and run the game again"),
+                    (WatcherCommand::RestartOnlyUi, "Restart in ONLY_UI", "exit and run only the editor ui, without the game's systems"),
+                    (WatcherCommand::Stop, "Stop", "exit and stop watching"),
+                ] {
+                    if ui.button(label).on_hover_text(hover).clicked() {
+                        connection.command(command);
+                    }
+                }
+            });
+        });
+
+        let input = display_terminal(
+            &mut terminal_state.terminal_view,
+            &mut terminal_state.terminal,
+            connection.output(),
             ui,
-            terminal_state.auto_scroll,
         );
+        if let Some(line) = input {
+            let message = EditorToWatcher::Stdin(format!("{line}\n").into_bytes());
+            connection.send(message);
+        }
+        if let Some(location) = terminal_state.terminal_view.clicked_location() {
+            if let Some(code) = cx.state_mut::<CodeEditor>() {
+                code.open_location(&location);
+            }
+        }
     }
 }
 
-#[derive(Default)]
+/// An editor window the plugin registers.
+struct PluginWindow {
+    add: fn(&mut App),
+    dock: DockWindow,
+}
+
+impl PluginWindow {
+    fn of<W: EditorWindow>() -> PluginWindow {
+        PluginWindow {
+            add: |app| {
+                app.add_editor_window::<W>();
+            },
+            dock: DockWindow::of::<W>(),
+        }
+    }
+}
+
+fn default_windows() -> Vec<PluginWindow> {
+    use bevy_editor_pls_default_windows::add::AddWindow;
+    use bevy_editor_pls_default_windows::assets::AssetsWindow;
+    use bevy_editor_pls_default_windows::cameras::CameraWindow;
+    use bevy_editor_pls_default_windows::debug_settings::DebugSettingsWindow;
+    use bevy_editor_pls_default_windows::diagnostics::DiagnosticsWindow;
+    use bevy_editor_pls_default_windows::gizmos::GizmoWindow;
+    use bevy_editor_pls_default_windows::hierarchy::HierarchyWindow;
+    use bevy_editor_pls_default_windows::inspector::InspectorWindow;
+    use bevy_editor_pls_default_windows::renderer::RendererWindow;
+    use bevy_editor_pls_default_windows::resources::ResourcesWindow;
+
+    vec![
+        PluginWindow::of::<HierarchyWindow>(),
+        PluginWindow::of::<AssetsWindow>(),
+        PluginWindow::of::<InspectorWindow>(),
+        PluginWindow::of::<DebugSettingsWindow>(),
+        PluginWindow::of::<AddWindow>(),
+        PluginWindow::of::<DiagnosticsWindow>(),
+        PluginWindow::of::<RendererWindow>(),
+        PluginWindow::of::<CameraWindow>(),
+        PluginWindow::of::<ResourcesWindow>(),
+        PluginWindow {
+            dock: DockWindow::of::<ScenesWindow>().next_to::<HierarchyWindow>(),
+            ..PluginWindow::of::<ScenesWindow>()
+        },
+        PluginWindow::of::<GizmoWindow>(),
+        PluginWindow::of::<controls::ControlsWindow>(),
+        PluginWindow::of::<CodeEditor>(),
+        PluginWindow::of::<Terminal>(),
+        PluginWindow::of::<BuildFailure>(),
+        PluginWindow::of::<LayoutWindow>(),
+        PluginWindow {
+            dock: DockWindow::of::<ReflectionWindow>().next_to::<InspectorWindow>(),
+            ..PluginWindow::of::<ReflectionWindow>()
+        },
+    ]
+}
+
+/// The editor, with the bevy_editor_pls windows and ours. Which windows it
+/// has is up to [`without`](Self::without) and [`with_window`](Self::with_window),
+/// or the project's [`EditorConfig`].
 pub struct EditorPlugin {
     pub window: EditorWindowPlacement,
+    windows: Vec<PluginWindow>,
+    /// Saved in `assets/scenes`, spawned at startup instead of the game's setup.
+    scene: Option<String>,
+    reset_markers: Vec<ResetMarker>,
+    preserve_edits: Vec<fn(&mut App)>,
+}
+
+impl Default for EditorPlugin {
+    fn default() -> Self {
+        EditorPlugin {
+            window: EditorWindowPlacement::default(),
+            windows: default_windows(),
+            scene: None,
+            reset_markers: Vec::new(),
+            preserve_edits: Vec::new(),
+        }
+    }
 }
 
 impl EditorPlugin {
@@ -68,21 +179,116 @@ impl EditorPlugin {
         EditorPlugin::default()
     }
 
+    /// The editor as the project's `.bevy_editor/editor.ron` wants it. A
+    /// broken config is reported and the defaults are used.
+    pub fn from_config() -> Self {
+        match EditorConfig::load(&std::env::current_dir().unwrap_or_default()) {
+            Ok(config) => EditorPlugin::new().with_config(&config),
+            Err(err) => {
+                eprintln!("{err}, using the default editor");
+                EditorPlugin::new()
+            }
+        }
+    }
+
+    pub fn with_config(mut self, config: &EditorConfig) -> Self {
+        self.windows
+            .retain(|window| !config.without.iter().any(|name| name == window.dock.name()));
+        self.scene = config.scene.clone();
+        match config.window {
+            WindowPlacement::GameWindow => self,
+            WindowPlacement::NewWindow => self.in_new_window(Window::default()),
+            WindowPlacement::Fullscreen { monitor } => self.on_monitor_fullscreen(monitor),
+        }
+    }
+
+    /// Leaves out one of the windows the editor comes with.
+    pub fn without<W: EditorWindow>(mut self) -> Self {
+        self.windows
+            .retain(|window| window.dock.id() != std::any::TypeId::of::<W>());
+        self
+    }
+
+    /// Adds a window of your own. Unless the layout says otherwise, it's
+    /// tabbed with the terminal.
+    pub fn with_window<W: EditorWindow>(self) -> Self {
+        self.with_window_next_to::<W, Terminal>()
+    }
+
+    /// Adds a window of your own, tabbed with `Neighbour` unless the layout
+    /// says otherwise, or with the game view if there's no `Neighbour`.
+    pub fn with_window_next_to<W: EditorWindow, Neighbour: EditorWindow>(self) -> Self {
+        let mut plugin = self.without::<W>();
+        plugin.windows.push(PluginWindow {
+            dock: DockWindow::of::<W>().next_to::<Neighbour>(),
+            ..PluginWindow::of::<W>()
+        });
+        plugin
+    }
+
+    /// Starts the game with the scene saved as `name` in `assets/scenes`.
+    pub fn with_startup_scene(mut self, name: impl Into<String>) -> Self {
+        self.scene = Some(name.into());
+        self
+    }
+
+    /// Tells the editor about a marker the game's `reset_setup` uses. Edits
+    /// made in the inspector to the entities it marks are put back after the
+    /// setup respawns them on a hot reload, matched up by their [`Name`],
+    /// unless they're [`ResetOnReload`].
+    /// Saving a scene leaves the marker out of the file and takes it off the
+    /// saved entities, so hot reloads keep them instead of despawning them and running the setup again.
+    ///
+    /// [`ResetOnReload`]: crate::preserve::ResetOnReload
+    /// [`Name`]: bevy::prelude::Name
+    pub fn with_reset_marker<M: Component>(mut self) -> Self {
+        self.reset_markers.push(ResetMarker::of::<M>());
+        self.preserve_edits.push(preserve::add::<M>);
+        self
+    }
+
     /// Start the editor in a new window. Use [`Window::default`] for creating a new window with default settings.
     pub fn in_new_window(mut self, window: Window) -> Self {
         self.window = EditorWindowPlacement::New(window);
         self
     }
-    /// Start the editor on the second window ([`MonitorSelection::Index(1)`].
-    pub fn on_second_monitor_fullscreen(self) -> Self {
+    /// Start the editor borderless fullscreen on the monitor with this index ([`MonitorSelection::Index`]).
+    pub fn on_monitor_fullscreen(self, monitor: usize) -> Self {
         self.in_new_window(Window {
-            // TODO: just use `mode: BorderlessFullscreen` https://github.com/bevyengine/bevy/pull/8178
             resolution: WindowResolution::new(1920.0, 1080.0),
-            position: WindowPosition::Centered(MonitorSelection::Index(1)),
-            decorations: false,
+            position: WindowPosition::Centered(MonitorSelection::Index(monitor)),
+            mode: WindowMode::BorderlessFullscreen,
             ..Default::default()
         })
     }
+    /// Start the editor on the second monitor ([`MonitorSelection::Index(1)`]).
+    pub fn on_second_monitor_fullscreen(self) -> Self {
+        self.on_monitor_fullscreen(1)
+    }
+}
+
+/// The project's directory name, the manager names projects after them.
+fn project_name() -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|dir| Some(dir.file_name()?.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "bevy_editor_pls".to_string())
+}
+
+/// Borderless fullscreen windows open on the primary monitor whatever their
+/// position says (https://github.com/bevyengine/bevy/pull/8178), so these open
+/// windowed on their monitor and go fullscreen once they're there.
+#[derive(Component)]
+struct FullscreenOnceOpen;
+
+fn go_fullscreen(
+    mut commands: Commands,
+    mut windows: Query<(Entity, &mut Window), (With<FullscreenOnceOpen>, With<RawHandleWrapper>)>,
+) {
+    for (entity, mut window) in &mut windows {
+        window.mode = WindowMode::BorderlessFullscreen;
+        commands.entity(entity).remove::<FullscreenOnceOpen>();
+    }
 }
 
 impl Plugin for EditorPlugin {
@@ -91,10 +297,19 @@ impl Plugin for EditorPlugin {
             EditorWindowPlacement::New(ref window) => {
                 let mut window = window.clone();
                 if window.title == "Bevy App" {
-                    window.title = "bevy_editor_pls".into();
+                    window.title = format!("{} editor", project_name());
                 }
-                let entity = app.world.spawn(window);
-                WindowRef::Entity(entity.id())
+                let on_chosen_monitor = window.mode == WindowMode::BorderlessFullscreen
+                    && matches!(window.position, WindowPosition::Centered(MonitorSelection::Index(_)));
+                let entity = if on_chosen_monitor {
+                    window.mode = WindowMode::Windowed;
+                    window.decorations = false;
+                    app.add_systems(Update, go_fullscreen);
+                    app.world.spawn((window, FullscreenOnceOpen)).id()
+                } else {
+                    app.world.spawn(window).id()
+                };
+                WindowRef::Entity(entity)
             }
             EditorWindowPlacement::Window(entity) => WindowRef::Entity(entity),
             EditorWindowPlacement::Primary => WindowRef::Primary,
@@ -107,58 +322,32 @@ impl Plugin for EditorPlugin {
         //     app.add_plugins(bevy_framepace::debug::DiagnosticsPlugin);
         // }
 
-        {
-            use bevy_editor_pls_default_windows::add::AddWindow;
-            use bevy_editor_pls_default_windows::assets::AssetsWindow;
-            use bevy_editor_pls_default_windows::cameras::CameraWindow;
-            use bevy_editor_pls_default_windows::debug_settings::DebugSettingsWindow;
-            use bevy_editor_pls_default_windows::diagnostics::DiagnosticsWindow;
-            use bevy_editor_pls_default_windows::gizmos::GizmoWindow;
-            use bevy_editor_pls_default_windows::hierarchy::HierarchyWindow;
-            use bevy_editor_pls_default_windows::inspector::InspectorWindow;
-            use bevy_editor_pls_default_windows::renderer::RendererWindow;
-            use bevy_editor_pls_default_windows::resources::ResourcesWindow;
-            use bevy_editor_pls_default_windows::scenes::SceneWindow;
-
-            app.add_editor_window::<HierarchyWindow>();
-            app.add_editor_window::<AssetsWindow>();
-            app.add_editor_window::<InspectorWindow>();
-            app.add_editor_window::<DebugSettingsWindow>();
-            app.add_editor_window::<AddWindow>();
-            app.add_editor_window::<DiagnosticsWindow>();
-            app.add_editor_window::<RendererWindow>();
-            app.add_editor_window::<CameraWindow>();
-            app.add_editor_window::<ResourcesWindow>();
-            app.add_editor_window::<SceneWindow>();
-            app.add_editor_window::<GizmoWindow>();
-            app.add_editor_window::<controls::ControlsWindow>();
-
-            app.add_editor_window::<CodeEditor>();
-            //app.add_editor_window::<Terminal>();
-
-            app.add_plugins(bevy::pbr::wireframe::WireframePlugin);
-
-            app.insert_resource(controls::EditorControls::default_bindings())
-                .add_systems(Update, controls::editor_controls_system);
-
-            let mut internal_state = app.world.resource_mut::<editor::EditorInternalState>();
-
-            let [game, _inspector] =
-                internal_state.split_right::<InspectorWindow>(egui_dock::NodeIndex::root(), 0.75);
-            internal_state.push_to_focused_leaf::<CodeEditor>();
-            let [game, _hierarchy] = internal_state.split_left::<HierarchyWindow>(game, 0.2);
-            let [_game, _bottom] = internal_state.split_many(
-                game,
-                0.8,
-                egui_dock::Split::Below,
-                &[
-                    //std::any::TypeId::of::<Terminal>(),
-                    std::any::TypeId::of::<ResourcesWindow>(),
-                    std::any::TypeId::of::<AssetsWindow>(),
-                    std::any::TypeId::of::<DebugSettingsWindow>(),
-                    std::any::TypeId::of::<DiagnosticsWindow>(),
-                ],
-            );
+        for window in &self.windows {
+            (window.add)(app);
+        }
+
+        app.init_resource::<WatcherConnection>()
+            .init_resource::<BuildStatus>()
+            .add_systems(Update, (exit_when_flushed, track_builds));
+
+        app.add_plugins(bevy::pbr::wireframe::WireframePlugin);
+
+        app.insert_resource(controls::EditorControls::default_bindings())
+            .add_systems(Update, controls::editor_controls_system);
+
+        let windows = self.windows.iter().map(|window| window.dock.clone()).collect();
+        let mut layout = EditorLayout::new(windows);
+        layout.apply(&mut app.world.resource_mut::<editor::EditorInternalState>());
+        app.insert_resource(layout)
+            .add_systems(Update, switch_layout)
+            .add_systems(PostUpdate, save_layout_on_exit);
+
+        app.insert_resource(ProjectScene::new(self.scene.clone(), self.reset_markers.clone()))
+            .add_systems(Startup, load_startup_scene)
+            .add_systems(Update, open_pending_scene);
+        app.register_type::<ResetOnReload>();
+        for add in &self.preserve_edits {
+            add(app);
         }
     }
 }
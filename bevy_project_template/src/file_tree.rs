@@ -0,0 +1,424 @@
+use bevy_editor_pls::egui::{Align, Key, RichText, ScrollArea, TextEdit, Ui};
+use crossbeam_channel::Receiver;
+use ignore::WalkBuilder;
+use notify::event::{AccessKind, AccessMode, ModifyKind};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Never shown, even if the project's .gitignore forgets them.
+const ALWAYS_HIDDEN: [&str; 2] = ["target", ".git"];
+
+/// Everything under `root` that belongs to the project: gitignored files,
+/// `target` and `.git` are skipped.
+pub fn walk(root: &Path) -> ignore::Walk {
+    WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|entry| !ALWAYS_HIDDEN.iter().any(|hidden| entry.file_name() == *hidden))
+        .build()
+}
+
+struct Entry {
+    path: PathBuf,
+    name: String,
+    is_dir: bool,
+}
+
+/// What happened in the tree that whoever has files open should know about.
+pub enum FileTreeEvent {
+    Open(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+    Deleted(PathBuf),
+    /// Something (maybe us) wrote to, created or removed this file.
+    Changed(PathBuf),
+}
+
+#[derive(Clone, PartialEq)]
+enum Edit {
+    NewFile { parent: PathBuf },
+    NewFolder { parent: PathBuf },
+    Rename { path: PathBuf },
+    Delete { path: PathBuf },
+}
+
+/// The project's files, read once and then kept up to date from filesystem
+/// notifications instead of walking the disk every frame.
+pub struct FileTree {
+    root: PathBuf,
+    /// Sorted children of every directory we show, folders first.
+    children: HashMap<PathBuf, Vec<Entry>>,
+    watcher: Option<RecommendedWatcher>,
+    watched: HashSet<PathBuf>,
+    changes: Receiver<notify::Result<notify::Event>>,
+    stale: bool,
+    open_dirs: HashSet<PathBuf>,
+    reveal: Option<PathBuf>,
+    /// The edit in progress and the name typed for it so far.
+    edit: Option<(Edit, String)>,
+    focus_edit: bool,
+    error: Option<String>,
+}
+
+impl FileTree {
+    pub fn new(root: PathBuf) -> Self {
+        let (tx, changes) = crossbeam_channel::unbounded();
+        let (watcher, error) = match notify::recommended_watcher(move |event| {
+            tx.send(event).ok();
+        }) {
+            Ok(watcher) => (Some(watcher), None),
+            Err(err) => (None, Some(format!("not watching for file changes: {err}"))),
+        };
+        FileTree {
+            root,
+            children: HashMap::new(),
+            watcher,
+            watched: HashSet::new(),
+            changes,
+            stale: true,
+            open_dirs: HashSet::new(),
+            reveal: None,
+            edit: None,
+            focus_edit: false,
+            error,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Opens the folders leading to `path` and scrolls it into view.
+    pub fn reveal(&mut self, path: &Path) {
+        let mut parent = path.parent();
+        while let Some(dir) = parent.filter(|dir| dir.starts_with(&self.root)) {
+            self.open_dirs.insert(dir.to_path_buf());
+            parent = dir.parent();
+        }
+        self.reveal = Some(path.to_path_buf());
+    }
+
+    fn start_edit(&mut self, edit: Edit, name: String) {
+        self.edit = Some((edit, name));
+        self.focus_edit = true;
+    }
+
+    fn is_hidden(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return true;
+        };
+        relative
+            .components()
+            .any(|component| ALWAYS_HIDDEN.iter().any(|hidden| component.as_os_str() == *hidden))
+    }
+
+    fn refresh(&mut self) {
+        self.stale = false;
+        self.children.clear();
+        let walk = walk(&self.root);
+        let mut dirs = HashSet::from([self.root.clone()]);
+        for entry in walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    self.error = Some(err.to_string());
+                    continue;
+                }
+            };
+            if entry.depth() == 0 {
+                continue;
+            }
+            let Some(parent) = entry.path().parent() else {
+                continue;
+            };
+            let is_dir = entry.file_type().is_some_and(|file_type| file_type.is_dir());
+            if is_dir {
+                dirs.insert(entry.path().to_path_buf());
+            }
+            self.children.entry(parent.to_path_buf()).or_default().push(Entry {
+                path: entry.path().to_path_buf(),
+                name: entry.file_name().to_string_lossy().into_owned(),
+                is_dir,
+            });
+        }
+        for children in self.children.values_mut() {
+            children.sort_by_key(|entry| (!entry.is_dir, entry.name.to_lowercase()));
+        }
+        self.watch(dirs);
+    }
+
+    /// Every shown folder is watched on its own, so `target/` churning during a
+    /// build doesn't wake us up.
+    fn watch(&mut self, dirs: HashSet<PathBuf>) {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        for gone in self.watched.difference(&dirs) {
+            watcher.unwatch(gone).ok();
+        }
+        for new in dirs.difference(&self.watched) {
+            if let Err(err) = watcher.watch(new, RecursiveMode::NonRecursive) {
+                self.error = Some(format!("can't watch {}: {err}", new.display()));
+            }
+        }
+        self.watched = dirs;
+    }
+
+    fn apply(&mut self, edit: &Edit, name: &str) -> io::Result<Option<FileTreeEvent>> {
+        let name = name.trim();
+        if name.is_empty() && !matches!(edit, Edit::Delete { .. }) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the name can't be empty"));
+        }
+        self.stale = true;
+        match edit {
+            Edit::NewFile { parent } => {
+                let path = parent.join(name);
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::File::options().write(true).create_new(true).open(&path)?;
+                self.reveal(&path);
+                Ok(Some(FileTreeEvent::Open(path)))
+            }
+            Edit::NewFolder { parent } => {
+                let path = parent.join(name);
+                std::fs::create_dir_all(&path)?;
+                self.open_dirs.insert(path);
+                Ok(None)
+            }
+            Edit::Rename { path } => {
+                let to = path.with_file_name(name);
+                if to.exists() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} already exists", to.display()),
+                    ));
+                }
+                std::fs::rename(path, &to)?;
+                Ok(Some(FileTreeEvent::Renamed { from: path.clone(), to }))
+            }
+            Edit::Delete { path } => {
+                if path.is_dir() {
+                    std::fs::remove_dir_all(path)?;
+                } else {
+                    std::fs::remove_file(path)?;
+                }
+                Ok(Some(FileTreeEvent::Deleted(path.clone())))
+            }
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, selected: Option<&Path>) -> Vec<FileTreeEvent> {
+        let mut events = Vec::new();
+        let changes = self.changes.try_iter().collect::<Vec<_>>();
+        for change in changes {
+            let change = match change {
+                Ok(change) => change,
+                Err(err) => {
+                    self.error = Some(err.to_string());
+                    continue;
+                }
+            };
+            let paths = change.paths.into_iter().filter(|path| !self.is_hidden(path));
+            match change.kind {
+                // only the contents changed, the tree stays the same
+                EventKind::Modify(ModifyKind::Data(_))
+                | EventKind::Access(AccessKind::Close(AccessMode::Write)) => {
+                    events.extend(paths.map(FileTreeEvent::Changed));
+                }
+                EventKind::Access(_) => {}
+                _ => {
+                    let before = events.len();
+                    events.extend(paths.map(FileTreeEvent::Changed));
+                    self.stale |= events.len() > before;
+                }
+            }
+        }
+        if self.stale {
+            self.refresh();
+        }
+
+        let mut event = None;
+        ui.horizontal(|ui| {
+            if ui.button(egui_phosphor::regular::FILE_PLUS).on_hover_text("new file").clicked() {
+                self.start_edit(Edit::NewFile { parent: self.root.clone() }, String::new());
+            }
+            if ui.button(egui_phosphor::regular::FOLDER_PLUS).on_hover_text("new folder").clicked() {
+                self.start_edit(Edit::NewFolder { parent: self.root.clone() }, String::new());
+            }
+            if ui.button(egui_phosphor::regular::ARROWS_CLOCKWISE).on_hover_text("refresh").clicked() {
+                self.stale = true;
+            }
+        });
+        if let Some(err) = self.error.clone() {
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+                if ui.small_button(egui_phosphor::regular::X).clicked() {
+                    self.error = None;
+                }
+            });
+        }
+        ScrollArea::vertical()
+            .id_source("file tree")
+            .auto_shrink([true, false])
+            .show(ui, |ui| {
+                let root = self.root.clone();
+                self.dir_ui(ui, &root, 0, selected, &mut event);
+            });
+        events.extend(event);
+        events
+    }
+
+    fn dir_ui(
+        &mut self,
+        ui: &mut Ui,
+        dir: &Path,
+        depth: usize,
+        selected: Option<&Path>,
+        event: &mut Option<FileTreeEvent>,
+    ) {
+        if let Some((Edit::NewFile { parent } | Edit::NewFolder { parent }, _)) = &self.edit {
+            if parent == dir {
+                self.edit_ui(ui, depth, event);
+            }
+        }
+        let Some(children) = self.children.get(dir) else {
+            return;
+        };
+        let children = children
+            .iter()
+            .map(|entry| (entry.path.clone(), entry.name.clone(), entry.is_dir))
+            .collect::<Vec<_>>();
+        for (path, name, is_dir) in children {
+            let editing = matches!(
+                &self.edit,
+                Some((Edit::Rename { path: edited } | Edit::Delete { path: edited }, _)) if *edited == path
+            );
+            if editing {
+                self.edit_ui(ui, depth, event);
+            } else {
+                self.entry_ui(ui, &path, &name, is_dir, depth, selected, event);
+            }
+            if is_dir && self.open_dirs.contains(&path) {
+                self.dir_ui(ui, &path, depth + 1, selected, event);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn entry_ui(
+        &mut self,
+        ui: &mut Ui,
+        path: &Path,
+        name: &str,
+        is_dir: bool,
+        depth: usize,
+        selected: Option<&Path>,
+        event: &mut Option<FileTreeEvent>,
+    ) {
+        let open = self.open_dirs.contains(path);
+        let label = if is_dir {
+            let (caret, folder) = if open {
+                (egui_phosphor::regular::CARET_DOWN, egui_phosphor::regular::FOLDER_OPEN)
+            } else {
+                (egui_phosphor::regular::CARET_RIGHT, egui_phosphor::regular::FOLDER)
+            };
+            format!("{caret} {folder} {name}")
+        } else if name.ends_with(".rs") {
+            format!("   {} {name}", egui_phosphor::regular::FILE_RS)
+        } else {
+            format!("   {} {name}", egui_phosphor::regular::FILE)
+        };
+        let response = ui
+            .horizontal(|ui| {
+                ui.add_space(depth as f32 * 12.0);
+                ui.selectable_label(selected == Some(path), RichText::new(label))
+            })
+            .inner;
+        if response.clicked() {
+            if !is_dir {
+                *event = Some(FileTreeEvent::Open(path.to_path_buf()));
+            } else if open {
+                self.open_dirs.remove(path);
+            } else {
+                self.open_dirs.insert(path.to_path_buf());
+            }
+        }
+        if self.reveal.as_deref() == Some(path) {
+            response.scroll_to_me(Some(Align::Center));
+            self.reveal = None;
+        }
+        response.context_menu(|ui| {
+            if is_dir {
+                if ui.button("New file").clicked() {
+                    self.open_dirs.insert(path.to_path_buf());
+                    self.start_edit(Edit::NewFile { parent: path.to_path_buf() }, String::new());
+                    ui.close_menu();
+                }
+                if ui.button("New folder").clicked() {
+                    self.open_dirs.insert(path.to_path_buf());
+                    self.start_edit(Edit::NewFolder { parent: path.to_path_buf() }, String::new());
+                    ui.close_menu();
+                }
+            }
+            if ui.button("Rename").clicked() {
+                self.start_edit(Edit::Rename { path: path.to_path_buf() }, name.to_string());
+                ui.close_menu();
+            }
+            if ui.button("Delete").clicked() {
+                self.start_edit(Edit::Delete { path: path.to_path_buf() }, name.to_string());
+                ui.close_menu();
+            }
+        });
+    }
+
+    fn edit_ui(&mut self, ui: &mut Ui, depth: usize, event: &mut Option<FileTreeEvent>) {
+        let Some((edit, mut name)) = self.edit.take() else {
+            return;
+        };
+        let mut done = false;
+        let mut confirmed = false;
+        ui.horizontal(|ui| {
+            ui.add_space(depth as f32 * 12.0);
+            if let Edit::Delete { .. } = edit {
+                ui.label(format!("Delete {name}?"));
+                confirmed = ui.button("Delete").clicked();
+                done = ui.button("Cancel").clicked();
+                return;
+            }
+            let hint = match edit {
+                Edit::NewFile { .. } => "file name",
+                Edit::NewFolder { .. } => "folder name",
+                _ => "new name",
+            };
+            let response = ui.add(TextEdit::singleline(&mut name).hint_text(hint));
+            if std::mem::take(&mut self.focus_edit) {
+                response.request_focus();
+            }
+            let (enter, escape) = ui.input(|input| {
+                (input.key_pressed(Key::Enter), input.key_pressed(Key::Escape))
+            });
+            confirmed = response.lost_focus() && enter;
+            done = escape;
+        });
+        if confirmed {
+            match self.apply(&edit, &name) {
+                Ok(applied) => {
+                    *event = applied.or(event.take());
+                    done = true;
+                }
+                Err(err) => self.error = Some(err.to_string()),
+            }
+        }
+        if !done {
+            self.edit = Some((edit, name));
+        }
+    }
+}
+
+impl Default for FileTree {
+    fn default() -> Self {
+        FileTree::new(std::env::current_dir().unwrap_or_default())
+    }
+}
@@ -0,0 +1,50 @@
+use crate::syntax::Language;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Whether we know how to format files of `language`.
+pub fn can_format(language: Language) -> bool {
+    matches!(language, Language::Rust | Language::Toml)
+}
+
+/// Runs `text` through the formatter for `language`. The formatter runs in
+/// the file's directory so it finds the project's rustfmt.toml / taplo.toml.
+pub fn format(language: Language, path: &Path, text: &str) -> Result<String, String> {
+    let (program, args, install) = match language {
+        Language::Rust => ("rustfmt", &["--edition", "2021"][..], "rustup component add rustfmt"),
+        Language::Toml => ("taplo", &["fmt", "-"][..], "cargo install taplo-cli"),
+        _ => return Err(format!("no formatter for {}", language.name())),
+    };
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(dir) = path.parent() {
+        command.current_dir(dir);
+    }
+    let mut child = command.spawn().map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => format!("{program} isn't installed, get it with `{install}`"),
+        _ => format!("couldn't run {program}: {err}"),
+    })?;
+    // a big file would fill the pipes, write from another thread while we read
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = text.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("couldn't run {program}: {err}"))?;
+    writer
+        .join()
+        .expect("the writer doesn't panic")
+        .map_err(|err| format!("couldn't pass the file to {program}: {err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        // rustfmt calls the input <stdin>, put the file's name back
+        return Err(format!("{program} failed on {name}:\n{}", stderr.trim().replace("<stdin>", &name)));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{program} didn't output UTF-8"))
+}
@@ -0,0 +1,285 @@
+use bevy::input::keyboard::KeyCode;
+use bevy_editor_pls::controls::{Action, Button, EditorControls, UserInput};
+use bevy_editor_pls::egui::{Event, Key, Modifiers, Ui};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// What a shortcut does in the code editor.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum EditorAction {
+    Save,
+    SaveAll,
+    CloseTab,
+    Find,
+    Format,
+    Undo,
+    Redo,
+    GoToDefinition,
+    Complete,
+}
+
+impl EditorAction {
+    pub const ALL: [EditorAction; 9] = [
+        EditorAction::Save,
+        EditorAction::SaveAll,
+        EditorAction::CloseTab,
+        EditorAction::Find,
+        EditorAction::Format,
+        EditorAction::Undo,
+        EditorAction::Redo,
+        EditorAction::GoToDefinition,
+        EditorAction::Complete,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EditorAction::Save => "Save",
+            EditorAction::SaveAll => "Save all",
+            EditorAction::CloseTab => "Close tab",
+            EditorAction::Find => "Find and replace",
+            EditorAction::Format => "Format document",
+            EditorAction::Undo => "Undo",
+            EditorAction::Redo => "Redo",
+            EditorAction::GoToDefinition => "Go to definition",
+            EditorAction::Complete => "Complete",
+        }
+    }
+
+    fn default_binding(self) -> KeyBinding {
+        let (modifiers, key) = match self {
+            EditorAction::Save => (Modifiers::COMMAND, Key::S),
+            EditorAction::SaveAll => (Modifiers::COMMAND | Modifiers::SHIFT, Key::S),
+            EditorAction::CloseTab => (Modifiers::COMMAND, Key::W),
+            EditorAction::Find => (Modifiers::COMMAND, Key::F),
+            EditorAction::Format => (Modifiers::ALT | Modifiers::SHIFT, Key::F),
+            EditorAction::Undo => (Modifiers::COMMAND, Key::Z),
+            EditorAction::Redo => (Modifiers::COMMAND | Modifiers::SHIFT, Key::Z),
+            EditorAction::GoToDefinition => (Modifiers::NONE, Key::F12),
+            EditorAction::Complete => (Modifiers::COMMAND, Key::Space),
+        };
+        KeyBinding { modifiers, key }
+    }
+}
+
+/// A key with modifiers, written like `Ctrl+Shift+S` in the settings. Ctrl
+/// means Cmd on macOS.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyBinding {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl KeyBinding {
+    fn modifier_count(&self) -> usize {
+        [self.modifiers.command, self.modifiers.shift, self.modifiers.alt]
+            .into_iter()
+            .filter(|held| *held)
+            .count()
+    }
+
+    /// Whether bevy_editor_pls' `input` is the same keys.
+    fn same_as(&self, input: &UserInput) -> bool {
+        let buttons = match input {
+            UserInput::Single(button) => std::slice::from_ref(button),
+            UserInput::Chord(buttons) => buttons.as_slice(),
+        };
+        let mut modifiers = Modifiers::NONE;
+        let mut keys = Vec::new();
+        for button in buttons {
+            match button {
+                Button::Keyboard(KeyCode::ControlLeft | KeyCode::ControlRight)
+                | Button::Keyboard(KeyCode::SuperLeft | KeyCode::SuperRight) => modifiers.command = true,
+                Button::Keyboard(KeyCode::ShiftLeft | KeyCode::ShiftRight) => modifiers.shift = true,
+                Button::Keyboard(KeyCode::AltLeft | KeyCode::AltRight) => modifiers.alt = true,
+                Button::Keyboard(code) => keys.push(*code),
+                Button::Mouse(_) => return false,
+            }
+        }
+        let same_modifiers = modifiers.command == self.modifiers.command
+            && modifiers.shift == self.modifiers.shift
+            && modifiers.alt == self.modifiers.alt;
+        same_modifiers && matches!(keys.as_slice(), [code] if same_key(self.key, *code))
+    }
+}
+
+/// egui names keys by what they are, bevy by where they are on a US keyboard.
+fn same_key(key: Key, code: KeyCode) -> bool {
+    let name = key.name();
+    let code = format!("{code:?}");
+    code == name
+        || code == format!("Key{name}")
+        || code == format!("Digit{name}")
+        || code == format!("Arrow{name}")
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.command {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{}", self.key.name())
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = Modifiers::NONE;
+        let mut parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+        let key = parts.pop().unwrap_or_default();
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "cmd" => modifiers.command = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                _ => return Err(format!("unknown modifier {part:?} in {text:?}")),
+            }
+        }
+        let key = Key::from_name(key).ok_or_else(|| format!("unknown key {key:?} in {text:?}"))?;
+        Ok(KeyBinding { modifiers, key })
+    }
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<KeyBinding> for String {
+    fn from(binding: KeyBinding) -> Self {
+        binding.to_string()
+    }
+}
+
+/// The code editor's shortcuts: the defaults with the user's overrides on
+/// top. Shortcuts bevy_editor_pls' [`EditorControls`] already use are left
+/// out, otherwise both would fire.
+pub struct Keybindings {
+    bindings: Vec<(EditorAction, KeyBinding)>,
+    /// Bindings we ignore and what of bevy_editor_pls they collide with.
+    conflicts: Vec<(EditorAction, KeyBinding, String)>,
+}
+
+impl Keybindings {
+    pub fn new(overrides: &BTreeMap<EditorAction, KeyBinding>, controls: Option<&EditorControls>) -> Self {
+        let mut bindings = Vec::new();
+        let mut conflicts = Vec::new();
+        for action in EditorAction::ALL {
+            let binding = overrides
+                .get(&action)
+                .copied()
+                .unwrap_or_else(|| action.default_binding());
+            let taken_by = controls.and_then(|controls| {
+                controls.actions.iter().find_map(|(editor_action, editor_bindings)| {
+                    editor_bindings
+                        .iter()
+                        .any(|editor_binding| binding.same_as(&editor_binding.input))
+                        .then(|| format!("{editor_action:?}"))
+                })
+            });
+            match taken_by {
+                Some(editor_action) => conflicts.push((action, binding, editor_action)),
+                None => bindings.push((action, binding)),
+            }
+        }
+        // most specific first, so Ctrl+Shift+S isn't taken for Ctrl+S
+        bindings.sort_by_key(|(_, binding)| std::cmp::Reverse(binding.modifier_count()));
+        Keybindings { bindings, conflicts }
+    }
+
+    pub fn binding(&self, action: EditorAction) -> Option<KeyBinding> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, binding)| *binding)
+    }
+
+    pub fn conflicts(&self) -> &[(EditorAction, KeyBinding, String)] {
+        &self.conflicts
+    }
+
+    /// Takes this frame's shortcuts out of the input, before the text edit
+    /// gets to see them and types or undoes on its own.
+    pub fn take_actions(&self, ui: &Ui) -> Vec<EditorAction> {
+        ui.input_mut(|input| {
+            let mut actions = Vec::new();
+            for (action, binding) in &self.bindings {
+                if input.consume_key(binding.modifiers, binding.key) {
+                    actions.push(*action);
+                }
+            }
+            // some platforms still send the letter of a Ctrl shortcut as text,
+            // Ctrl+Alt is AltGr on Windows though, which types real characters
+            if input.modifiers.command && !input.modifiers.alt {
+                input.events.retain(|event| !matches!(event, Event::Text(_)));
+            }
+            actions
+        })
+    }
+}
+
+/// Waits for the next key press to rebind an action.
+pub fn capture(ui: &Ui) -> Option<KeyBinding> {
+    ui.input_mut(|input| {
+        let (index, key, modifiers) = input.events.iter().enumerate().find_map(|(index, event)| match event {
+            Event::Key { key, pressed: true, modifiers, .. } => Some((index, *key, *modifiers)),
+            _ => None,
+        })?;
+        input.events.remove(index);
+        // only the platform independent `command`, like the defaults
+        let modifiers = Modifiers {
+            alt: modifiers.alt,
+            shift: modifiers.shift,
+            command: modifiers.command,
+            ..Modifiers::NONE
+        };
+        Some(KeyBinding { modifiers, key })
+    })
+}
+
+/// An [`EditorControls`] input the way a person would write it, `Ctrl+Enter`.
+pub fn describe(input: &UserInput) -> String {
+    let buttons = match input {
+        UserInput::Single(button) => std::slice::from_ref(button),
+        UserInput::Chord(buttons) => buttons.as_slice(),
+    };
+    buttons
+        .iter()
+        .map(|button| match button {
+            Button::Keyboard(KeyCode::ControlLeft | KeyCode::ControlRight) => "Ctrl".to_string(),
+            Button::Keyboard(KeyCode::ShiftLeft | KeyCode::ShiftRight) => "Shift".to_string(),
+            Button::Keyboard(KeyCode::AltLeft | KeyCode::AltRight) => "Alt".to_string(),
+            Button::Keyboard(KeyCode::SuperLeft | KeyCode::SuperRight) => "Super".to_string(),
+            Button::Keyboard(code) => {
+                let name = format!("{code:?}");
+                let name = name.strip_prefix("Key").or(name.strip_prefix("Digit")).unwrap_or(&name);
+                name.to_string()
+            }
+            Button::Mouse(button) => format!("{button:?} click"),
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// What hides and shows the editor, if anything does.
+pub fn editor_toggle(controls: &EditorControls) -> Option<String> {
+    let bindings = controls.actions.get(&Action::PlayPauseEditor)?;
+    let inputs = bindings
+        .iter()
+        .map(|binding| describe(&binding.input))
+        .collect::<Vec<_>>();
+    (!inputs.is_empty()).then(|| inputs.join(" or "))
+}
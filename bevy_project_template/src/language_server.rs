@@ -0,0 +1,356 @@
+use crate::buffer::Buffer;
+use crate::keybindings::EditorAction;
+use crate::syntax::Language;
+use bevy_editor_common::lsp::{CompletionItem, Diagnostic, LspClient, LspEvent, Position, Severity};
+use bevy_editor_pls::egui::epaint::text::cursor::CCursor;
+use bevy_editor_pls::egui::text_edit::TextEditOutput;
+use bevy_editor_pls::egui::{
+    self, Color32, Frame, Id, Key, Order, Pos2, RichText, ScrollArea, Shape,
+    Stroke, Ui,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How long the pointer has to rest on something before we ask for a hover.
+const HOVER_DELAY: f32 = 0.4;
+
+struct Completion {
+    path: PathBuf,
+    /// Char index where the word being completed starts.
+    word_start: usize,
+    items: Vec<CompletionItem>,
+}
+
+/// rust-analyzer for the code editor: started the first time a Rust file is
+/// open, fed every change to the open Rust buffers, and drawn on top of the
+/// editor (squiggles, hovers, completion).
+#[derive(Default)]
+pub struct LanguageServer {
+    client: Option<LspClient>,
+    /// Why there's no client, we only try to start it once.
+    error: Option<String>,
+    /// What the server thinks each open file contains.
+    synced: HashMap<PathBuf, String>,
+    hover_requested: Option<(PathBuf, Position)>,
+    hover: Option<(PathBuf, Position, String)>,
+    completion: Option<Completion>,
+    /// The last request rust-analyzer answered with an error.
+    failed: Option<String>,
+}
+
+impl LanguageServer {
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Opens, updates and closes documents on the server to match `buffers`.
+    pub fn sync(&mut self, buffers: &[Buffer], root: &Path) {
+        let rust = buffers
+            .iter()
+            .filter(|buffer| buffer.language == Language::Rust && !buffer.read_only);
+        if self.client.is_none() && self.error.is_none() && rust.clone().next().is_some() {
+            match LspClient::rust_analyzer(root) {
+                Ok(client) => self.client = Some(client),
+                Err(err) => self.error = Some(format!("couldn't start rust-analyzer: {err}")),
+            }
+        }
+        let Some(client) = self.client.as_mut() else {
+            return;
+        };
+        let mut open = Vec::new();
+        for buffer in rust {
+            open.push(buffer.path.clone());
+            match self.synced.get(&buffer.path) {
+                Some(synced) if *synced == buffer.text => {}
+                Some(_) => client.did_change(&buffer.path, &buffer.text),
+                None => client.did_open(&buffer.path, "rust", &buffer.text),
+            }
+            if self.synced.get(&buffer.path) != Some(&buffer.text) {
+                self.synced.insert(buffer.path.clone(), buffer.text.clone());
+            }
+        }
+        self.synced.retain(|path, _| {
+            let keep = open.contains(path);
+            if !keep {
+                client.did_close(path);
+            }
+            keep
+        });
+    }
+
+    pub fn saved(&mut self, path: &Path) {
+        if let Some(client) = self.client.as_mut() {
+            client.did_save(path);
+        }
+    }
+
+    /// Returns where go-to-definition wants to take us.
+    pub fn poll(&mut self) -> Option<(PathBuf, Position)> {
+        let client = self.client.as_mut()?;
+        let mut jump = None;
+        for event in client.poll() {
+            match event {
+                LspEvent::Diagnostics(_) => {}
+                LspEvent::Hover { path, position, text } => {
+                    self.hover = text.map(|text| (path, position, text));
+                }
+                LspEvent::Definition { locations, .. } => {
+                    jump = locations
+                        .into_iter()
+                        .next()
+                        .map(|location| (location.path, location.range.start));
+                }
+                LspEvent::Completion { path, items, .. } => {
+                    if let Some(completion) = self.completion.as_mut().filter(|c| c.path == path) {
+                        completion.items = items;
+                    }
+                }
+                LspEvent::Error(err) => self.failed = Some(err),
+                LspEvent::Exited => {
+                    self.error = Some("rust-analyzer exited".to_string());
+                    self.client = None;
+                    self.synced.clear();
+                    break;
+                }
+            }
+        }
+        jump
+    }
+
+    pub fn diagnostics(&self, path: &Path) -> &[Diagnostic] {
+        self.client
+            .as_ref()
+            .map_or(&[], |client| client.diagnostics(path))
+    }
+
+    /// Squiggles, hover, ctrl+click to go to the definition and the
+    /// definition and completion shortcuts, for the buffer that was just drawn
+    /// into `output`.
+    pub fn editor_ui(&mut self, ui: &mut Ui, buffer: &mut Buffer, output: &TextEditOutput, actions: &[EditorAction]) {
+        if self.client.is_none() || buffer.language != Language::Rust {
+            return;
+        }
+        let diagnostics = self.diagnostics(&buffer.path).to_vec();
+        paint_squiggles(ui, &buffer.text, &diagnostics, output);
+
+        let cursor = output
+            .cursor_range
+            .map(|range| range.primary.ccursor.index);
+        let Some(client) = self.client.as_mut() else {
+            return;
+        };
+
+        let ctrl_click = output.response.clicked() && ui.input(|input| input.modifiers.command);
+        if ctrl_click || actions.contains(&EditorAction::GoToDefinition) {
+            if let Some(cursor) = cursor {
+                client.definition(&buffer.path, Position::from_char_index(&buffer.text, cursor));
+            }
+        }
+
+        if let (true, Some(cursor)) = (actions.contains(&EditorAction::Complete), cursor) {
+            client.completion(&buffer.path, Position::from_char_index(&buffer.text, cursor));
+            self.completion = Some(Completion {
+                path: buffer.path.clone(),
+                word_start: word_start(&buffer.text, cursor),
+                items: Vec::new(),
+            });
+        }
+
+        self.hover_ui(ui, buffer, output, &diagnostics);
+        if let Some(cursor) = cursor {
+            self.completion_ui(ui, buffer, output, cursor);
+        }
+    }
+
+    fn hover_ui(&mut self, ui: &mut Ui, buffer: &Buffer, output: &TextEditOutput, diagnostics: &[Diagnostic]) {
+        let Some(pointer) = output.response.hover_pos() else {
+            return;
+        };
+        let index = output
+            .galley
+            .cursor_from_pos(pointer - output.galley_pos)
+            .ccursor
+            .index;
+        let position = Position::from_char_index(&buffer.text, index);
+        let resting = ui.input(|input| input.pointer.time_since_last_movement()) > HOVER_DELAY;
+        let key = (buffer.path.clone(), position);
+        if resting && self.hover_requested.as_ref() != Some(&key) {
+            if let Some(client) = self.client.as_mut() {
+                client.hover(&buffer.path, position);
+            }
+            self.hover = None;
+            self.hover_requested = Some(key);
+        }
+        if !resting {
+            return;
+        }
+        let hover = self
+            .hover
+            .as_ref()
+            .filter(|(path, at, _)| *path == buffer.path && *at == position)
+            .map(|(_, _, text)| text.clone());
+        let under_pointer = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.range.start <= position && position < diagnostic.range.end)
+            .collect::<Vec<_>>();
+        if hover.is_none() && under_pointer.is_empty() {
+            return;
+        }
+        egui::show_tooltip_at_pointer(ui.ctx(), Id::new("language server hover"), |ui| {
+            ui.set_max_width(500.0);
+            for diagnostic in under_pointer {
+                ui.label(RichText::new(&diagnostic.message).color(severity_color(diagnostic.severity)));
+            }
+            if let Some(hover) = hover {
+                ui.label(RichText::new(hover).monospace());
+            }
+        });
+    }
+
+    fn completion_ui(&mut self, ui: &mut Ui, buffer: &mut Buffer, output: &TextEditOutput, cursor: usize) {
+        let Some(completion) = self.completion.as_ref() else {
+            return;
+        };
+        let escape = ui.input(|input| input.key_pressed(Key::Escape));
+        if completion.path != buffer.path || cursor < completion.word_start || escape {
+            self.completion = None;
+            return;
+        }
+        let prefix = buffer
+            .text
+            .chars()
+            .skip(completion.word_start)
+            .take(cursor - completion.word_start)
+            .collect::<String>();
+        let rect = output
+            .galley
+            .pos_from_ccursor(CCursor::new(completion.word_start))
+            .translate(output.galley_pos.to_vec2());
+        let mut picked = None;
+        egui::Area::new(Id::new("language server completion"))
+            .order(Order::Foreground)
+            .fixed_pos(Pos2::new(rect.left(), rect.bottom()))
+            .show(ui.ctx(), |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        let matching = completion
+                            .items
+                            .iter()
+                            .filter(|item| item.label.starts_with(&prefix));
+                        let mut any = false;
+                        for item in matching {
+                            any = true;
+                            let response = ui.selectable_label(false, &item.label);
+                            let response = match &item.detail {
+                                Some(detail) => response.on_hover_text(detail),
+                                None => response,
+                            };
+                            if response.clicked() {
+                                picked = Some(item.insert_text.clone());
+                            }
+                        }
+                        if !any {
+                            ui.weak("no completions");
+                        }
+                    });
+                });
+            });
+        let Some(insert) = picked else {
+            return;
+        };
+        let start = byte_index(&buffer.text, completion.word_start);
+        let end = byte_index(&buffer.text, cursor);
+        buffer.text.replace_range(start..end, &insert);
+        let end = completion.word_start + insert.chars().count();
+        buffer.jump_to = Some(end..end);
+        self.completion = None;
+    }
+}
+
+fn word_start(text: &str, cursor: usize) -> usize {
+    let before = text.chars().take(cursor).collect::<Vec<_>>();
+    let word = before
+        .iter()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || **c == '_')
+        .count();
+    cursor - word
+}
+
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(index, _)| index)
+}
+
+pub fn severity_color(severity: Severity) -> Color32 {
+    match severity {
+        Severity::Error => Color32::from_rgb(240, 80, 80),
+        Severity::Warning => Color32::from_rgb(230, 190, 60),
+        Severity::Information => Color32::from_rgb(90, 160, 240),
+        Severity::Hint => Color32::GRAY,
+    }
+}
+
+fn paint_squiggles(ui: &Ui, text: &str, diagnostics: &[Diagnostic], output: &TextEditOutput) {
+    let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+    let galley = &output.galley;
+    let offset = output.galley_pos.to_vec2();
+    // hints are too noisy to underline, they're in the problems list
+    for diagnostic in diagnostics.iter().filter(|d| d.severity != Severity::Hint) {
+        let start = galley.from_ccursor(CCursor::new(diagnostic.range.start.to_char_index(text)));
+        let end = galley.from_ccursor(CCursor::new(diagnostic.range.end.to_char_index(text)));
+        let start_rect = galley.pos_from_cursor(&start).translate(offset);
+        let right = if end.rcursor.row == start.rcursor.row {
+            galley.pos_from_cursor(&end).translate(offset).left()
+        } else {
+            galley.rows[start.rcursor.row].rect.right() + offset.x
+        };
+        // zero width ranges still get a short squiggle
+        let right = right.max(start_rect.left() + 6.0);
+        let y = start_rect.bottom() - 1.0;
+        let mut points = Vec::new();
+        let mut x = start_rect.left();
+        let mut up = false;
+        while x <= right {
+            points.push(Pos2::new(x, if up { y - 1.5 } else { y + 1.5 }));
+            up = !up;
+            x += 3.0;
+        }
+        painter.add(Shape::line(points, Stroke::new(1.0, severity_color(diagnostic.severity))));
+    }
+}
+
+/// The problems of every open Rust file, clicking one returns where it is.
+pub fn problems_ui(server: &LanguageServer, ui: &mut Ui, root: &Path) -> Option<(PathBuf, Position)> {
+    let client = server.client.as_ref()?;
+    let all = client.all_diagnostics();
+    let count = all.iter().map(|(_, diagnostics)| diagnostics.len()).sum::<usize>();
+    let mut clicked = None;
+    egui::CollapsingHeader::new(format!("Problems ({count})"))
+        .id_source("code editor problems")
+        .show(ui, |ui| {
+            if let Some(failed) = &server.failed {
+                ui.weak(format!("rust-analyzer: {failed}"));
+            }
+            ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                for (path, diagnostics) in all {
+                    let name = path.strip_prefix(root).unwrap_or(path).display().to_string();
+                    for diagnostic in diagnostics {
+                        let start = diagnostic.range.start;
+                        let text = RichText::new(format!(
+                            "{name}:{}:{}  {}",
+                            start.line + 1,
+                            start.character + 1,
+                            diagnostic.message.lines().next().unwrap_or_default(),
+                        ))
+                        .color(severity_color(diagnostic.severity));
+                        if ui.selectable_label(false, text).clicked() {
+                            clicked = Some((path.to_path_buf(), start));
+                        }
+                    }
+                }
+            });
+        });
+    clicked
+}
@@ -0,0 +1,455 @@
+use crate::build_failure::BuildFailure;
+use crate::code_editor::CodeEditor;
+use crate::editor_plugin::Terminal;
+use crate::keybindings;
+use bevy::app::AppExit;
+use bevy::prelude::{EventReader, Mut, ResMut, Resource, World};
+use bevy::window::WindowCloseRequested;
+use bevy_editor_pls::controls::EditorControls;
+use bevy_editor_pls::egui::{self, Ui};
+use bevy_editor_pls::egui_dock::{NodeIndex, Split};
+use bevy_editor_pls_core::editor::EditorInternalState;
+use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
+use bevy_editor_pls_default_windows::assets::AssetsWindow;
+use bevy_editor_pls_default_windows::debug_settings::DebugSettingsWindow;
+use bevy_editor_pls_default_windows::diagnostics::DiagnosticsWindow;
+use bevy_editor_pls_default_windows::hierarchy::HierarchyWindow;
+use bevy_editor_pls_default_windows::inspector::InspectorWindow;
+use bevy_editor_pls_default_windows::renderer::RendererWindow;
+use bevy_editor_pls_default_windows::resources::ResourcesWindow;
+use serde::{Deserialize, Serialize};
+use std::any::TypeId;
+use std::path::PathBuf;
+
+/// Where a project keeps its layout, relative to the project directory.
+pub const LAYOUT_FILE: &str = ".bevy_editor/layout.ron";
+
+/// The tab name the game view goes by in a layout.
+pub const GAME_VIEW: &str = "Game View";
+
+/// How the dock is split up. Tabs are named by their window's `NAME`, the game
+/// view by [`GAME_VIEW`], which has to be in there exactly once.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum LayoutNode {
+    Tabs(Vec<String>),
+    /// `fraction` is how much of the width goes to `left`.
+    Horizontal {
+        fraction: f32,
+        left: Box<LayoutNode>,
+        right: Box<LayoutNode>,
+    },
+    /// `fraction` is how much of the height goes to `top`.
+    Vertical {
+        fraction: f32,
+        top: Box<LayoutNode>,
+        bottom: Box<LayoutNode>,
+    },
+}
+
+impl LayoutNode {
+    fn tabs(names: &[&str]) -> LayoutNode {
+        LayoutNode::Tabs(names.iter().map(|name| name.to_string()).collect())
+    }
+
+    fn horizontal(fraction: f32, left: LayoutNode, right: LayoutNode) -> LayoutNode {
+        LayoutNode::Horizontal {
+            fraction,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    fn vertical(fraction: f32, top: LayoutNode, bottom: LayoutNode) -> LayoutNode {
+        LayoutNode::Vertical {
+            fraction,
+            top: Box::new(top),
+            bottom: Box::new(bottom),
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        match self {
+            LayoutNode::Tabs(tabs) => tabs.iter().any(|tab| tab == name),
+            LayoutNode::Horizontal { left: a, right: b, .. }
+            | LayoutNode::Vertical { top: a, bottom: b, .. } => a.contains(name) || b.contains(name),
+        }
+    }
+
+    /// Adds `name` to the tabs `neighbour` is in, if it's anywhere.
+    fn insert_next_to(&mut self, name: &str, neighbour: &str) -> bool {
+        match self {
+            LayoutNode::Tabs(tabs) if tabs.iter().any(|tab| tab == neighbour) => {
+                tabs.push(name.to_string());
+                true
+            }
+            LayoutNode::Tabs(_) => false,
+            LayoutNode::Horizontal { left: a, right: b, .. }
+            | LayoutNode::Vertical { top: a, bottom: b, .. } => {
+                a.insert_next_to(name, neighbour) || b.insert_next_to(name, neighbour)
+            }
+        }
+    }
+
+    fn first_tabs(&self) -> &[String] {
+        match self {
+            LayoutNode::Tabs(tabs) => tabs,
+            LayoutNode::Horizontal { left: first, .. } | LayoutNode::Vertical { top: first, .. } => {
+                first.first_tabs()
+            }
+        }
+    }
+
+    /// Drops the tabs of windows that aren't around and whatever ends up empty.
+    fn prune(self, known: &dyn Fn(&str) -> bool) -> Option<LayoutNode> {
+        let split = |fraction, a: Box<LayoutNode>, b: Box<LayoutNode>, build: fn(f32, LayoutNode, LayoutNode) -> LayoutNode| {
+            match (a.prune(known), b.prune(known)) {
+                (Some(a), Some(b)) => Some(build(fraction, a, b)),
+                (a, b) => a.or(b),
+            }
+        };
+        match self {
+            LayoutNode::Tabs(tabs) => {
+                let tabs = tabs
+                    .into_iter()
+                    .filter(|tab| tab == GAME_VIEW || known(tab))
+                    .collect::<Vec<_>>();
+                (!tabs.is_empty()).then_some(LayoutNode::Tabs(tabs))
+            }
+            LayoutNode::Horizontal { fraction, left, right } => split(fraction, left, right, LayoutNode::horizontal),
+            LayoutNode::Vertical { fraction, top, bottom } => split(fraction, top, bottom, LayoutNode::vertical),
+        }
+    }
+}
+
+/// The layouts that come with the editor.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Preset {
+    SceneEditing,
+    Code,
+    Debug,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::SceneEditing, Preset::Code, Preset::Debug];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::SceneEditing => "Scene editing",
+            Preset::Code => "Code",
+            Preset::Debug => "Debug",
+        }
+    }
+
+    pub fn layout(self) -> LayoutNode {
+        use LayoutNode as N;
+        match self {
+            // what the editor always started with
+            Preset::SceneEditing => N::horizontal(
+                0.75,
+                N::horizontal(
+                    0.2,
+                    N::tabs(&[HierarchyWindow::NAME]),
+                    N::vertical(
+                        0.8,
+                        N::tabs(&[GAME_VIEW, CodeEditor::NAME]),
+                        N::tabs(&[
+                            Terminal::NAME,
+                            ResourcesWindow::NAME,
+                            AssetsWindow::NAME,
+                            DebugSettingsWindow::NAME,
+                            DiagnosticsWindow::NAME,
+                            LayoutWindow::NAME,
+                        ]),
+                    ),
+                ),
+                N::tabs(&[InspectorWindow::NAME]),
+            ),
+            Preset::Code => N::horizontal(
+                0.6,
+                N::vertical(
+                    0.75,
+                    N::tabs(&[CodeEditor::NAME]),
+                    N::tabs(&[Terminal::NAME, LayoutWindow::NAME]),
+                ),
+                N::vertical(
+                    0.5,
+                    N::tabs(&[GAME_VIEW]),
+                    N::tabs(&[InspectorWindow::NAME, HierarchyWindow::NAME]),
+                ),
+            ),
+            Preset::Debug => N::vertical(
+                0.6,
+                N::horizontal(
+                    0.75,
+                    N::tabs(&[GAME_VIEW]),
+                    N::tabs(&[InspectorWindow::NAME, ResourcesWindow::NAME]),
+                ),
+                N::horizontal(
+                    0.5,
+                    N::tabs(&[Terminal::NAME, CodeEditor::NAME]),
+                    N::tabs(&[
+                        DiagnosticsWindow::NAME,
+                        DebugSettingsWindow::NAME,
+                        RendererWindow::NAME,
+                        LayoutWindow::NAME,
+                    ]),
+                ),
+            ),
+        }
+    }
+}
+
+/// A window the layout can put somewhere.
+#[derive(Clone)]
+pub struct DockWindow {
+    name: &'static str,
+    id: TypeId,
+    push_to_focused_leaf: fn(&mut EditorInternalState),
+    /// Where it goes when the layout doesn't mention it, tabbed with this
+    /// window. Without one it's only in the "Open window" menu.
+    next_to: Option<&'static str>,
+}
+
+impl DockWindow {
+    pub fn of<W: EditorWindow>() -> DockWindow {
+        DockWindow {
+            name: W::NAME,
+            id: TypeId::of::<W>(),
+            push_to_focused_leaf: |state| state.push_to_focused_leaf::<W>(),
+            next_to: None,
+        }
+    }
+
+    pub fn next_to<W: EditorWindow>(mut self) -> DockWindow {
+        self.next_to = Some(W::NAME);
+        self
+    }
+
+    pub fn id(&self) -> TypeId {
+        self.id
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// The layout the dock was last set up with, and the one to switch to.
+///
+/// It's saved to [`LAYOUT_FILE`] when a layout is picked and again when the
+/// editor closes, and restored on startup. bevy_editor_pls keeps the dock
+/// itself private, so what's saved is the layout the dock was built from,
+/// tabs dragged around by hand since then aren't in it. The resource lives
+/// outside the reloadable part of the app, hot reloads leave the dock alone.
+#[derive(Resource)]
+pub struct EditorLayout {
+    windows: Vec<DockWindow>,
+    current: LayoutNode,
+    pending: Option<LayoutNode>,
+    status: Option<String>,
+}
+
+impl EditorLayout {
+    pub fn new(windows: Vec<DockWindow>) -> EditorLayout {
+        let (current, status) = match load() {
+            Ok(Some(layout)) => (layout, None),
+            Ok(None) => (Preset::SceneEditing.layout(), None),
+            Err(err) => (Preset::SceneEditing.layout(), Some(err)),
+        };
+        EditorLayout {
+            windows,
+            current,
+            pending: None,
+            status,
+        }
+    }
+
+    /// Switches to `layout` next frame, it can't happen while the editor is drawn.
+    pub fn switch_to(&mut self, layout: LayoutNode) {
+        self.pending = Some(layout);
+    }
+
+    fn window(&self, name: &str) -> Option<&DockWindow> {
+        self.windows.iter().find(|window| window.name == name)
+    }
+
+    fn ids(&self, tabs: &[String]) -> Vec<TypeId> {
+        tabs.iter()
+            .filter_map(|tab| self.window(tab))
+            .map(|window| window.id)
+            .collect()
+    }
+
+    /// Sets up a fresh dock with the current layout.
+    pub fn apply(&mut self, state: &mut EditorInternalState) {
+        let known = |name: &str| self.window(name).is_some();
+        let mut layout = match self.current.clone().prune(&known) {
+            Some(layout) if layout.contains(GAME_VIEW) => layout,
+            _ => {
+                let fallback = Preset::SceneEditing.layout().prune(&known).unwrap();
+                self.status = Some(format!("the layout has no \"{GAME_VIEW}\" tab, using the default one"));
+                fallback
+            }
+        };
+        for window in &self.windows {
+            let Some(neighbour) = window.next_to else { continue };
+            if !layout.contains(window.name) && !layout.insert_next_to(window.name, neighbour) {
+                layout.insert_next_to(window.name, GAME_VIEW);
+            }
+        }
+        // the game view starts out alone in the focused leaf, fill that in before splitting it up
+        let game_leaf = game_leaf(&layout);
+        for tab in game_leaf.iter().filter(|tab| *tab != GAME_VIEW) {
+            if let Some(window) = self.window(tab) {
+                (window.push_to_focused_leaf)(state);
+            }
+        }
+        self.split(state, NodeIndex::root(), &layout, true);
+        // we're only running the ui because the game went down, say why up front
+        if std::env::var(bevy_editor_common::protocol::FAILURE_REPORT).is_ok()
+            && self.window(BuildFailure::NAME).is_some()
+            && !layout.contains(BuildFailure::NAME)
+        {
+            state.push_to_focused_leaf::<BuildFailure>();
+        }
+    }
+
+    /// `node` already holds the leaf with the game view if `game` is set, or
+    /// the first leaf of `layout` otherwise.
+    fn split(&self, state: &mut EditorInternalState, node: NodeIndex, layout: &LayoutNode, game: bool) {
+        let (fraction, a, b, towards_b, towards_a) = match layout {
+            LayoutNode::Tabs(_) => return,
+            LayoutNode::Horizontal { fraction, left, right } => (*fraction, left, right, Split::Right, Split::Left),
+            LayoutNode::Vertical { fraction, top, bottom } => (*fraction, top, bottom, Split::Below, Split::Above),
+        };
+        let (placed, other, direction) = if !game || a.contains(GAME_VIEW) {
+            (a, b, towards_b)
+        } else {
+            (b, a, towards_a)
+        };
+        // the dock's fraction is always the left or top share, whichever side is new
+        let [old, new] = state.split_many(node, fraction, direction, &self.ids(other.first_tabs()));
+        self.split(state, old, placed, game);
+        self.split(state, new, other, false);
+    }
+}
+
+fn game_leaf(layout: &LayoutNode) -> &[String] {
+    match layout {
+        LayoutNode::Tabs(tabs) => tabs,
+        LayoutNode::Horizontal { left: a, right: b, .. } | LayoutNode::Vertical { top: a, bottom: b, .. } => {
+            if a.contains(GAME_VIEW) {
+                game_leaf(a)
+            } else {
+                game_leaf(b)
+            }
+        }
+    }
+}
+
+fn layout_path() -> PathBuf {
+    std::env::current_dir().unwrap_or_default().join(LAYOUT_FILE)
+}
+
+/// No layout saved yet is fine, one that doesn't parse isn't.
+fn load() -> Result<Option<LayoutNode>, String> {
+    let path = layout_path();
+    let Ok(text) = std::fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    ron::from_str(&text)
+        .map(Some)
+        .map_err(|err| format!("couldn't read the layout from {}: {err}", path.display()))
+}
+
+fn save(layout: &LayoutNode) -> Result<(), String> {
+    let path = layout_path();
+    let text = ron::ser::to_string_pretty(layout, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    std::fs::write(&path, text)
+        .map_err(|err| format!("couldn't save the layout to {}: {err}", path.display()))
+}
+
+/// Rebuilds the dock when a layout was picked, outside of the editor's ui.
+pub fn switch_layout(world: &mut World) {
+    let Some(layout) = world.resource_mut::<EditorLayout>().pending.take() else {
+        return;
+    };
+    world.resource_scope(|world, mut editor_layout: Mut<EditorLayout>| {
+        editor_layout.current = layout;
+        let mut state = world.resource_mut::<EditorInternalState>();
+        *state = EditorInternalState::default();
+        editor_layout.apply(&mut state);
+        editor_layout.status = save(&editor_layout.current).err();
+    });
+}
+
+/// Saves the layout when the app exits or its window is closed, whichever
+/// comes first.
+pub fn save_layout_on_exit(
+    mut exits: EventReader<AppExit>,
+    mut closes: EventReader<WindowCloseRequested>,
+    mut layout: ResMut<EditorLayout>,
+) {
+    let exiting = exits.read().count() > 0;
+    let closing = closes.read().count() > 0;
+    if exiting || closing {
+        layout.status = save(&layout.current).err();
+    }
+}
+
+/// Picking presets and resetting the layout, and how to get the editor out of the way.
+pub struct LayoutWindow;
+
+impl EditorWindow for LayoutWindow {
+    type State = ();
+    const NAME: &'static str = "Layout";
+
+    fn ui(world: &mut World, _cx: EditorWindowContext, ui: &mut Ui) {
+        match world.get_resource::<EditorControls>().and_then(keybindings::editor_toggle) {
+            Some(toggle) => ui.label(format!("{toggle} hides the editor and shows the game through its own camera.")),
+            None => ui.label("Nothing is bound to hiding the editor, see PlayPauseEditor in the Controls window."),
+        };
+        let mut layout = world.resource_mut::<EditorLayout>();
+        ui.horizontal_wrapped(|ui| {
+            for preset in Preset::ALL {
+                if ui.button(preset.name()).clicked() {
+                    layout.switch_to(preset.layout());
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .button("Reset layout")
+                .on_hover_text("back to the layout the editor starts with")
+                .clicked()
+            {
+                layout.switch_to(Preset::SceneEditing.layout());
+            }
+            if ui
+                .button("Reload from file")
+                .on_hover_text(format!("after editing {LAYOUT_FILE} by hand"))
+                .clicked()
+            {
+                match load() {
+                    Ok(Some(loaded)) => layout.switch_to(loaded),
+                    Ok(None) => layout.status = Some(format!("there's no {LAYOUT_FILE} yet")),
+                    Err(err) => layout.status = Some(err),
+                }
+            }
+        });
+        ui.weak(format!("Saved to {LAYOUT_FILE} in the project when picked and on exit, tabs moved by hand aren't part of it."));
+        if let Some(status) = &layout.status {
+            ui.colored_label(ui.visuals().warn_fg_color, status);
+        }
+        ui.add_space(4.0);
+        egui::CollapsingHeader::new("Windows").show(ui, |ui| {
+            for window in &layout.windows {
+                ui.monospace(window.name);
+            }
+        });
+    }
+}
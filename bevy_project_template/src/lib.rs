@@ -1,12 +1,14 @@
+#[cfg(feature = "editor")]
 use crate::editor_plugin::EditorPlugin;
 use bevy::app::{App, AppExit, Last, Startup};
-use bevy::asset::Assets;
+use bevy::asset::{Assets, Handle};
 use bevy::math::Vec3;
 use bevy::pbr::{PbrBundle, PointLight, PointLightBundle, StandardMaterial};
 use bevy::prelude::{
-    default, Camera3dBundle, Color, Commands, Component, Cuboid, EventReader, Mesh, ResMut,
-    Transform,
+    default, Camera3dBundle, Color, Commands, Component, Cuboid, EventReader, Mesh, Name,
+    ReflectComponent, ResMut, Transform,
 };
+use bevy::reflect::Reflect;
 use bevy::DefaultPlugins;
 use dexterous_developer::{
     dexterous_developer_setup, hot_bevy_main, InitialPlugins, ReloadableApp, ReloadableAppContents,
@@ -14,15 +16,66 @@ use dexterous_developer::{
 };
 use std::process::exit;
 
+/// Has the editor's inspector pick the game's Reflect types up again on every
+/// hot reload, put it next to their definitions. Does nothing without the
+/// `editor` feature.
+#[macro_export]
+macro_rules! reloadable_types {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            #[cfg(feature = "editor")]
+            ::inventory::submit! {
+                $crate::reflection::ReloadableType(
+                    <$ty as ::bevy::reflect::GetTypeRegistration>::get_type_registration,
+                )
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "editor")]
+mod buffer;
+#[cfg(feature = "editor")]
+mod build_failure;
+#[cfg(feature = "editor")]
+mod build_status;
+#[cfg(feature = "editor")]
 mod editor_plugin;
+#[cfg(feature = "editor")]
 pub mod terminal;
+#[cfg(feature = "editor")]
 mod code_editor;
+#[cfg(feature = "editor")]
+mod file_tree;
+#[cfg(feature = "editor")]
+mod format;
+#[cfg(feature = "editor")]
+mod keybindings;
+#[cfg(feature = "editor")]
+mod language_server;
+#[cfg(feature = "editor")]
+mod layout;
+#[cfg(feature = "editor")]
+pub mod preserve;
+#[cfg(feature = "editor")]
+mod reflection;
+#[cfg(feature = "editor")]
+mod scene;
+#[cfg(feature = "editor")]
+mod search;
+#[cfg(feature = "editor")]
+mod settings;
+#[cfg(feature = "editor")]
+mod syntax;
+#[cfg(feature = "editor")]
+mod toast;
 
 #[hot_bevy_main]
 pub fn bevy_main(initial_plugins: impl InitialPlugins) {
     let mut app = App::new();
     app.add_plugins(initial_plugins.initialize::<DefaultPlugins>());
-    app.add_plugins(EditorPlugin::default());
+    #[cfg(feature = "editor")]
+    app.add_plugins(EditorPlugin::from_config().with_reset_marker::<GetRidOf>());
     app.add_systems(Last, |mut app_exit: EventReader<AppExit>| {
         if !app_exit.is_empty() {
             exit(0);
@@ -39,19 +92,34 @@ pub fn bevy_main(initial_plugins: impl InitialPlugins) {
     app.run();
 }
 
+/// Fixed ids rather than `Assets::add`, so scenes saved from the editor find
+/// the cube's mesh and material again in the next run.
+const CUBE_MESH: Handle<Mesh> = Handle::weak_from_u128(0x6c1f_0b5e_2d4a_4c59_9a8e_3f17_b2c4_d801);
+const CUBE_MATERIAL: Handle<StandardMaterial> = Handle::weak_from_u128(0x6c1f_0b5e_2d4a_4c59_9a8e_3f17_b2c4_d802);
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    #[cfg(feature = "editor")] scene: Option<bevy::prelude::Res<scene::ProjectScene>>,
 ) {
+    meshes.insert(CUBE_MESH, Cuboid::new(1.0, 1.0, 1.0).into());
+    materials.insert(CUBE_MATERIAL, Color::rgb_u8(21, 14, 25).into());
+    // a scene saved from the editor has taken over
+    #[cfg(feature = "editor")]
+    if scene.is_some_and(|scene| scene.current.is_some()) {
+        return;
+    }
     // cube
     commands.spawn((
         PbrBundle {
-            mesh: meshes.add(Cuboid::new(1.0, 1.0, 1.0)),
-            material: materials.add(Color::rgb_u8(21, 14, 25)),
+            mesh: CUBE_MESH,
+            material: CUBE_MATERIAL,
             transform: Transform::from_xyz(0.0, 0.5, 0.0),
             ..default()
         },
+        // what the editor finds it by to put inspector edits back after a reload
+        Name::new("Cube"),
         GetRidOf,
     ));
     // light
@@ -69,14 +137,24 @@ fn setup(
             transform: Transform::from_xyz(-2.5, 4.5, 9.0).looking_at(Vec3::ZERO, Vec3::Y),
             ..default()
         },
+        Name::new("Camera"),
         GetRidOf,
     ));
 }
 
-#[derive(Component)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 struct GetRidOf;
 
+reloadable_types!(GetRidOf);
+
 #[dexterous_developer_setup]
 fn reloadable(app: &mut ReloadableAppContents) {
     app.reset_setup::<GetRidOf, _>(setup);
+    // tells the editor's status bar when a build made it in
+    #[cfg(feature = "editor")]
+    app.add_systems(bevy::app::Update, build_status::count_loads);
+    // registers everything marked with reloadable_types!, `.register::<T>()` types from other crates
+    #[cfg(feature = "editor")]
+    app.add_systems(bevy::app::Update, reflection::ReloadableTypes::new().system());
 }
@@ -0,0 +1,162 @@
+use bevy::app::{App, PostUpdate};
+use bevy::ecs::component::Tick;
+use bevy::ecs::entity::EntityHashMap;
+use bevy::ecs::reflect::ReflectMapEntities;
+use bevy::pbr::{Cascades, CascadesVisibleEntities, CubemapVisibleEntities};
+use bevy::prelude::{
+    AppTypeRegistry, Component, Entity, GlobalTransform, InheritedVisibility, IntoSystemConfigs, Mut,
+    Name, ReflectComponent, Resource, ViewVisibility, With, World,
+};
+use bevy::reflect::Reflect;
+use bevy::render::primitives::{Aabb, CascadesFrusta, CubemapFrusta, Frustum};
+use bevy::render::view::VisibleEntities;
+use bevy::utils::HashMap;
+use bevy_editor_pls_core::editor::Editor;
+use bevy_editor_pls_core::EditorSet;
+use std::any::TypeId;
+use std::marker::PhantomData;
+
+/// Entities with this lose their inspector edits on a hot reload like
+/// everything used to.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct ResetOnReload;
+
+/// Worked out from other components every frame, an edit to these can't stick
+/// and a saved scene has no use for them.
+pub(crate) fn derived() -> [TypeId; 11] {
+    [
+        TypeId::of::<GlobalTransform>(),
+        TypeId::of::<InheritedVisibility>(),
+        TypeId::of::<ViewVisibility>(),
+        TypeId::of::<Aabb>(),
+        TypeId::of::<Frustum>(),
+        TypeId::of::<CubemapFrusta>(),
+        TypeId::of::<CascadesFrusta>(),
+        TypeId::of::<VisibleEntities>(),
+        TypeId::of::<CubemapVisibleEntities>(),
+        TypeId::of::<CascadesVisibleEntities>(),
+        TypeId::of::<Cascades>(),
+    ]
+}
+
+/// What's been edited in the inspector on the entities `reset_setup::<M, _>`
+/// respawns on every reload. Components the editor's ui changes are
+/// snapshotted through reflection, and put back when the setup spawns the
+/// entity again, whatever the game's own systems do to them meanwhile. The
+/// ones bevy derives from others, like [`GlobalTransform`], are left out, they
+/// follow along once their sources are back.
+///
+/// The respawned entity is found by its [`Name`], the setup can spawn things
+/// in any order. Entities without one, or sharing it with another, don't keep
+/// their edits.
+#[derive(Resource)]
+pub struct PreservedEdits<M> {
+    names: EntityHashMap<String>,
+    edits: HashMap<String, HashMap<TypeId, Box<dyn Reflect>>>,
+    /// When the editor's ui started this frame, what changed after that was
+    /// changed in the inspector.
+    editor_started: Tick,
+    marker: PhantomData<M>,
+}
+
+impl<M> Default for PreservedEdits<M> {
+    fn default() -> Self {
+        PreservedEdits {
+            names: EntityHashMap::default(),
+            edits: HashMap::default(),
+            editor_started: Tick::new(0),
+            marker: PhantomData,
+        }
+    }
+}
+
+pub fn add<M: Component>(app: &mut App) {
+    app.init_resource::<PreservedEdits<M>>().add_systems(
+        PostUpdate,
+        (
+            note_editor_start::<M>.before(EditorSet::UI),
+            preserve_edits::<M>.after(EditorSet::UI),
+        ),
+    );
+}
+
+fn note_editor_start<M: Component>(world: &mut World) {
+    let tick = world.read_change_tick();
+    world.resource_mut::<PreservedEdits<M>>().editor_started = tick;
+}
+
+fn preserve_edits<M: Component>(world: &mut World) {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let mut marked: Vec<(Entity, String)> = world
+        .query_filtered::<(Entity, &Name), With<M>>()
+        .iter(world)
+        .map(|(entity, name)| (entity, name.as_str().to_string()))
+        .collect();
+    // a name more than one of them has doesn't say which is which
+    let mut counts = HashMap::<String, usize>::default();
+    for (_, name) in &marked {
+        *counts.entry(name.clone()).or_default() += 1;
+    }
+    marked.retain(|(_, name)| counts[name] == 1);
+
+    world.resource_scope(|world, mut preserved: Mut<PreservedEdits<M>>| {
+        preserved
+            .names
+            .retain(|entity, _| marked.iter().any(|(marked, _)| marked == entity));
+
+        let mut spawned = Vec::new();
+        for (entity, name) in marked {
+            if preserved.names.contains_key(&entity) {
+                continue;
+            }
+            if let Some(edits) = preserved.edits.get(&name) {
+                let mut entity = world.entity_mut(entity);
+                for (type_id, component) in edits {
+                    if let Some(reflect) = registry.get_type_data::<ReflectComponent>(*type_id) {
+                        reflect.apply_or_insert(&mut entity, &**component, &registry);
+                    }
+                }
+            }
+            preserved.names.insert(entity, name);
+            spawned.push(entity);
+        }
+
+        if !world.get_resource::<Editor>().is_some_and(Editor::active) {
+            return;
+        }
+        // only what the editor's ui changed, not the game's systems
+        let (last_run, this_run) = (preserved.editor_started, world.read_change_tick());
+        let derived = derived();
+        let PreservedEdits { names, edits, .. } = &mut *preserved;
+        for (entity, name) in names.iter() {
+            let entity = world.entity(*entity);
+            if spawned.contains(&entity.id()) || entity.contains::<ResetOnReload>() {
+                continue;
+            }
+            for id in entity.archetype().components() {
+                let changed = entity
+                    .get_change_ticks_by_id(id)
+                    .is_some_and(|ticks| ticks.is_changed(last_run, this_run));
+                let Some(type_id) = world.components().get_info(id).and_then(|info| info.type_id()) else {
+                    continue;
+                };
+                // parents and children point at entities that won't be there after the reload
+                if !changed
+                    || derived.contains(&type_id)
+                    || registry.get_type_data::<ReflectMapEntities>(type_id).is_some()
+                {
+                    continue;
+                }
+                let reflect = registry.get_type_data::<ReflectComponent>(type_id);
+                if let Some(component) = reflect.and_then(|reflect| reflect.reflect(entity)) {
+                    edits
+                        .entry(name.clone())
+                        .or_default()
+                        .insert(type_id, component.clone_value());
+                }
+            }
+        }
+    });
+}
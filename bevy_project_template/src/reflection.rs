@@ -0,0 +1,144 @@
+use crate::scene::is_editor;
+use bevy::ecs::component::ComponentId;
+use bevy::prelude::{AppTypeRegistry, Local, ReflectComponent, Resource, World};
+use bevy::reflect::{GetTypeRegistration, TypeRegistration};
+use bevy_editor_pls::egui::{self, Ui};
+use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
+use std::collections::BTreeSet;
+
+/// The game's own types start with this.
+const GAME_CRATE: &str = concat!(env!("CARGO_CRATE_NAME"), "::");
+
+/// A type [`reloadable_types!`](crate::reloadable_types) put up for registration.
+pub struct ReloadableType(pub fn() -> TypeRegistration);
+
+inventory::collect!(ReloadableType);
+
+/// The game's reflectable types, registered again every time the library is
+/// reloaded so the inspector keeps up with new ones and with changed ones.
+/// Everything marked with [`reloadable_types!`](crate::reloadable_types) is picked up on
+/// its own, `register` is for types that can't be marked, from other crates.
+///
+/// ```ignore
+/// app.add_systems(Update, ReloadableTypes::new().register::<Spin>().system());
+/// ```
+#[derive(Default)]
+pub struct ReloadableTypes {
+    registrations: Vec<fn() -> TypeRegistration>,
+}
+
+impl ReloadableTypes {
+    pub fn new() -> Self {
+        ReloadableTypes::default()
+    }
+
+    pub fn register<T: GetTypeRegistration>(mut self) -> Self {
+        self.registrations.push(T::get_type_registration);
+        self
+    }
+
+    /// Has to be added to the reloadable part of the app, see
+    /// [`count_loads`](crate::build_status::count_loads).
+    pub fn system(self) -> impl FnMut(&mut World, Local<bool>) + Send + Sync + 'static {
+        move |world: &mut World, mut registered: Local<bool>| {
+            if *registered {
+                return;
+            }
+            *registered = true;
+            let registry = world.resource::<AppTypeRegistry>().clone();
+            let mut registry = registry.write();
+            let mut names = Vec::new();
+            let marked = inventory::iter::<ReloadableType>.into_iter().map(|marked| &marked.0);
+            for registration in marked.chain(&self.registrations) {
+                // replaces whatever the last library registered for the type
+                let registration = registration();
+                names.push(registration.type_info().type_path_table().short_path());
+                registry.add_registration(registration);
+            }
+            world.insert_resource(ReloadedTypes { names });
+        }
+    }
+}
+
+/// What [`ReloadableTypes`] registered on the last reload.
+#[derive(Resource)]
+pub struct ReloadedTypes {
+    names: Vec<&'static str>,
+}
+
+/// Why the inspector can't show a component.
+enum Invisible {
+    NotReflect,
+    NoReflectComponent,
+}
+
+/// Components on the game's entities that the inspector can't show.
+fn invisible_components(world: &World) -> Vec<(&str, Invisible)> {
+    let in_use: BTreeSet<ComponentId> = world
+        .iter_entities()
+        .filter(|entity| !is_editor(world, entity.id()))
+        .flat_map(|entity| entity.archetype().components().collect::<Vec<_>>())
+        .collect();
+    let registry = world.resource::<AppTypeRegistry>().read();
+    in_use
+        .into_iter()
+        .filter_map(|id| {
+            let info = world.components().get_info(id)?;
+            let invisible = match registry.get(info.type_id()?) {
+                None => Invisible::NotReflect,
+                Some(registration) if registration.data::<ReflectComponent>().is_none() => {
+                    Invisible::NoReflectComponent
+                }
+                Some(_) => return None,
+            };
+            Some((info.name(), invisible))
+        })
+        .collect()
+}
+
+/// Which of the game's types got registered on the last reload, and which of
+/// its components the inspector can't show.
+pub struct ReflectionWindow;
+
+impl EditorWindow for ReflectionWindow {
+    type State = ();
+    const NAME: &'static str = "Reflection";
+
+    fn ui(world: &mut World, _cx: EditorWindowContext, ui: &mut Ui) {
+        match world.get_resource::<ReloadedTypes>() {
+            Some(reloaded) if !reloaded.names.is_empty() => {
+                ui.label(format!("Registered on the last reload: {}.", reloaded.names.join(", ")));
+            }
+            _ => {
+                ui.label("Nothing registered on reload, mark the game's types with reloadable_types!.");
+            }
+        }
+        ui.add_space(4.0);
+
+        let (game, other): (Vec<_>, Vec<_>) = invisible_components(world)
+            .into_iter()
+            .partition(|(name, _)| name.starts_with(GAME_CRATE));
+        if game.is_empty() {
+            ui.label("The inspector can show all of the game's components.");
+        } else {
+            ui.label("The inspector can't show these:");
+        }
+        let list = |ui: &mut Ui, components: &[(&str, Invisible)]| {
+            for (name, invisible) in components {
+                let fix = match invisible {
+                    Invisible::NotReflect => "derive Reflect and register it",
+                    Invisible::NoReflectComponent => "add #[reflect(Component)]",
+                };
+                ui.horizontal(|ui| {
+                    ui.monospace(*name);
+                    ui.weak(fix);
+                });
+            }
+        };
+        list(ui, &game);
+        if !other.is_empty() {
+            egui::CollapsingHeader::new(format!("From other crates ({})", other.len()))
+                .show(ui, |ui| list(ui, &other));
+        }
+    }
+}
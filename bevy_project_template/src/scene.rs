@@ -0,0 +1,457 @@
+use crate::preserve::derived;
+use bevy::asset::{ReflectHandle, UntypedAssetId, UntypedHandle};
+use bevy::core_pipeline::core_2d::graph::Core2d;
+use bevy::core_pipeline::core_3d::graph::Core3d;
+use bevy::ecs::entity::EntityHashMap;
+use bevy::ecs::world::EntityWorldMut;
+use bevy::pbr::{Cascades, CascadesVisibleEntities, CubemapVisibleEntities, DirectionalLight, PointLight, SpotLight};
+use bevy::prelude::{
+    AppTypeRegistry, Camera, Camera2d, Camera3d, Component, Entity, GlobalTransform, InheritedVisibility, Parent,
+    ReflectComponent, Resource, Transform, ViewVisibility, Visibility, Window, World,
+};
+use bevy::render::camera::{CameraMainTextureUsages, CameraRenderGraph, Exposure};
+use bevy::render::primitives::{CascadesFrusta, CubemapFrusta, Frustum};
+use bevy::render::view::VisibleEntities;
+use bevy::scene::serde::SceneDeserializer;
+use bevy::scene::{DynamicScene, DynamicSceneBuilder, SceneFilter};
+use bevy_editor_common::config::{EditorConfig, SCENES_DIR};
+use bevy_editor_pls::egui::{self, Ui};
+use bevy_editor_pls_core::editor_window::{EditorWindow, EditorWindowContext};
+use bevy_editor_pls_default_windows::cameras::EditorCamera;
+use serde::de::DeserializeSeed;
+use std::any::TypeId;
+use std::path::PathBuf;
+
+/// The scene the world came from, and what the Scenes window is doing with it.
+#[derive(Resource)]
+pub struct ProjectScene {
+    /// Loaded at startup, opened or last saved to. The game's setup leaves the
+    /// world alone while there's one.
+    pub current: Option<String>,
+    /// What the next save is called.
+    name: String,
+    /// Opened on the next frame, outside of the editor's ui.
+    pending: Option<String>,
+    /// The `reset_setup` markers, see
+    /// [`EditorPlugin::with_reset_marker`](crate::editor_plugin::EditorPlugin::with_reset_marker).
+    reset_markers: Vec<ResetMarker>,
+    status: Option<String>,
+}
+
+impl ProjectScene {
+    /// Starts with `startup` if there's a file for it.
+    pub fn new(startup: Option<String>, reset_markers: Vec<ResetMarker>) -> Self {
+        let status = startup
+            .as_ref()
+            .filter(|name| !scene_path(name).exists())
+            .map(|name| format!("there's no {name}.scn.ron in {SCENES_DIR}, the game's setup built the world"));
+        let current = startup.filter(|name| scene_path(name).exists());
+        ProjectScene {
+            name: current.clone().unwrap_or_else(|| "main".to_string()),
+            current,
+            pending: None,
+            reset_markers,
+            status,
+        }
+    }
+}
+
+/// A marker the game's `reset_setup` uses. Saved scenes leave it out, and the
+/// entities that were saved lose it.
+#[derive(Clone, Copy)]
+pub struct ResetMarker {
+    type_id: TypeId,
+    unmark: fn(&mut World, &[Entity]),
+}
+
+impl ResetMarker {
+    pub fn of<M: Component>() -> ResetMarker {
+        ResetMarker {
+            type_id: TypeId::of::<M>(),
+            unmark: unmark::<M>,
+        }
+    }
+}
+
+fn unmark<M: Component>(world: &mut World, entities: &[Entity]) {
+    for &entity in entities {
+        if let Some(mut entity) = world.get_entity_mut(entity) {
+            entity.remove::<M>();
+        }
+    }
+}
+
+fn scenes_dir() -> PathBuf {
+    std::env::current_dir().unwrap_or_default().join(SCENES_DIR)
+}
+
+fn scene_path(name: &str) -> PathBuf {
+    EditorConfig::scene_path(&std::env::current_dir().unwrap_or_default(), name)
+}
+
+/// Editor cameras and windows, and everything under them.
+pub fn is_editor(world: &World, entity: Entity) -> bool {
+    let mut next = Some(entity);
+    while let Some(entity) = next.and_then(|entity| world.get_entity(entity)) {
+        if entity.contains::<EditorCamera>() || entity.contains::<Window>() {
+            return true;
+        }
+        next = entity.get::<Parent>().map(Parent::get);
+    }
+    false
+}
+
+/// The entities a scene is made of, everything but the editor's own.
+fn scene_entities(world: &World) -> Vec<Entity> {
+    world
+        .iter_entities()
+        .map(|entity| entity.id())
+        .filter(|&entity| !is_editor(world, entity))
+        .collect()
+}
+
+/// Can't be written down, put back by [`restore`] like the derived ones.
+fn unwritable() -> [TypeId; 3] {
+    [
+        TypeId::of::<CameraRenderGraph>(),
+        TypeId::of::<CameraMainTextureUsages>(),
+        TypeId::of::<Exposure>(),
+    ]
+}
+
+/// Gives a loaded entity back what the scene left out, as its bundle would
+/// have it. Bevy works out the real values from there, and adds `Aabb`s itself.
+fn restore(entity: &mut EntityWorldMut) {
+    if entity.contains::<Transform>() {
+        entity.insert(GlobalTransform::default());
+    }
+    if entity.contains::<Visibility>() {
+        entity.insert((InheritedVisibility::default(), ViewVisibility::default()));
+    }
+    if entity.contains::<Camera>() {
+        entity.insert((
+            Frustum::default(),
+            VisibleEntities::default(),
+            CameraMainTextureUsages::default(),
+            Exposure::default(),
+        ));
+    }
+    if entity.contains::<Camera3d>() {
+        entity.insert(CameraRenderGraph::new(Core3d));
+    }
+    if entity.contains::<Camera2d>() {
+        entity.insert(CameraRenderGraph::new(Core2d));
+    }
+    if entity.contains::<PointLight>() {
+        entity.insert((CubemapFrusta::default(), CubemapVisibleEntities::default()));
+    }
+    if entity.contains::<SpotLight>() {
+        entity.insert((Frustum::default(), VisibleEntities::default()));
+    }
+    if entity.contains::<DirectionalLight>() {
+        entity.insert((CascadesFrusta::default(), CascadesVisibleEntities::default(), Cascades::default()));
+    }
+}
+
+/// The world's entities as a scene, without the reset markers, the derived
+/// components or handles to assets that only exist in this run. Handles keep
+/// their asset when it has a fixed id (`Handle::weak_from_u128`), the game has
+/// to add it under that id again before the scene is loaded. Returns the
+/// entities and how many handles were left out.
+fn to_ron(world: &World) -> Result<(String, Vec<Entity>, usize), String> {
+    let entities = scene_entities(world);
+    let registry = world.resource::<AppTypeRegistry>();
+    let handles: Vec<(TypeId, ReflectHandle, ReflectComponent)> = registry
+        .read()
+        .iter()
+        .filter_map(|registration| {
+            let handle = registration.data::<ReflectHandle>()?.clone();
+            Some((registration.type_id(), handle, registration.data::<ReflectComponent>()?.clone()))
+        })
+        .collect();
+    let filter = world
+        .resource::<ProjectScene>()
+        .reset_markers
+        .iter()
+        .map(|marker| marker.type_id)
+        .chain(derived())
+        .chain(unwritable())
+        .chain(handles.iter().map(|(type_id, ..)| *type_id))
+        .fold(SceneFilter::allow_all(), |filter, type_id| filter.deny_by_id(type_id));
+    let mut scene = DynamicSceneBuilder::from_world(world)
+        .with_filter(filter)
+        .extract_entities(entities.iter().copied())
+        .build();
+    let mut left_out = 0;
+    for saved in &mut scene.entities {
+        let entity = world.entity(saved.entity);
+        for (_, handle, component) in &handles {
+            let Some(untyped) = component
+                .reflect(entity)
+                .and_then(|component| handle.downcast_handle_untyped(component.as_any()))
+            else {
+                continue;
+            };
+            match untyped.id() {
+                id @ UntypedAssetId::Uuid { .. } => saved.components.push(handle.typed(UntypedHandle::Weak(id))),
+                UntypedAssetId::Index { .. } => left_out += 1,
+            }
+        }
+    }
+    // bookkeeping entities with nothing reflectable in them
+    scene.entities.retain(|entity| !entity.components.is_empty());
+    let text = scene
+        .serialize_ron(registry)
+        .map_err(|err| format!("couldn't save the scene: {err}"))?;
+    Ok((text, entities, left_out))
+}
+
+/// Saves the world's entities as `name`, see [`to_ron`].
+fn save(world: &World, name: &str) -> Result<(Vec<Entity>, usize), String> {
+    let (text, entities, left_out) = to_ron(world)?;
+    let path = scene_path(name);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    std::fs::write(&path, text)
+        .map_err(|err| format!("couldn't save the scene to {}: {err}", path.display()))?;
+    Ok((entities, left_out))
+}
+
+fn parse(world: &World, text: &str) -> Result<DynamicScene, String> {
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let mut deserializer = ron::de::Deserializer::from_str(text).map_err(|err| err.to_string())?;
+    SceneDeserializer { type_registry: &registry }
+        .deserialize(&mut deserializer)
+        .map_err(|err| err.to_string())
+}
+
+/// Reads the scene saved as `name`, without touching the world.
+fn read(world: &World, name: &str) -> Result<DynamicScene, String> {
+    let path = scene_path(name);
+    let text = std::fs::read_to_string(&path)
+        .map_err(|err| format!("couldn't open {}: {err}", path.display()))?;
+    parse(world, &text).map_err(|err| format!("couldn't read {}: {err}", path.display()))
+}
+
+/// Spawns the scene saved as `name` into the world.
+fn load(world: &mut World, name: &str) -> Result<(), String> {
+    let scene = read(world, name)?;
+    spawn(world, &scene, name)
+}
+
+fn spawn(world: &mut World, scene: &DynamicScene, name: &str) -> Result<(), String> {
+    let mut entities = EntityHashMap::default();
+    scene
+        .write_to_world(world, &mut entities)
+        .map_err(|err| format!("couldn't spawn {name}: {err}"))?;
+    for entity in entities.into_values() {
+        restore(&mut world.entity_mut(entity));
+    }
+    Ok(())
+}
+
+/// Spawns the project's startup scene. Nothing marks its entities for
+/// `reset_setup`, so hot reloads keep them as they are.
+pub fn load_startup_scene(world: &mut World) {
+    let Some(name) = world.resource::<ProjectScene>().current.clone() else {
+        return;
+    };
+    let result = load(world, &name);
+    let mut scene = world.resource_mut::<ProjectScene>();
+    scene.status = result.err();
+}
+
+/// Swaps the world's entities for the scene picked in the Scenes window. A
+/// scene that doesn't read leaves the world as it is.
+pub fn open_pending_scene(world: &mut World) {
+    let Some(name) = world.resource_mut::<ProjectScene>().pending.take() else {
+        return;
+    };
+    let result = read(world, &name).and_then(|loaded| {
+        for entity in scene_entities(world) {
+            world.despawn(entity);
+        }
+        spawn(world, &loaded, &name)
+    });
+    let mut scene = world.resource_mut::<ProjectScene>();
+    match result {
+        Ok(()) => {
+            scene.current = Some(name);
+            scene.status = None;
+        }
+        Err(err) => scene.status = Some(err),
+    }
+}
+
+/// The scenes saved in the project.
+fn saved_scenes() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(scenes_dir()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            Some(name.strip_suffix(".scn.ron")?.to_string())
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// Saving the world to the project's `assets/scenes` and opening what's there.
+pub struct ScenesWindow;
+
+impl EditorWindow for ScenesWindow {
+    type State = ();
+    const NAME: &'static str = "Scenes";
+
+    fn ui(world: &mut World, _cx: EditorWindowContext, ui: &mut Ui) {
+        let mut name = world.resource::<ProjectScene>().name.clone();
+        let mut saved = None;
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut name).desired_width(120.0));
+            ui.weak(".scn.ron");
+            if ui
+                .add_enabled(!name.trim().is_empty(), egui::Button::new("Save"))
+                .on_hover_text(format!("everything but the editor's own entities, to {SCENES_DIR}"))
+                .clicked()
+            {
+                saved = Some(save(world, name.trim()));
+            }
+        });
+        if let Some(Ok((entities, _))) = &saved {
+            // hot reloads would respawn these from the game's setup otherwise
+            for marker in world.resource::<ProjectScene>().reset_markers.clone() {
+                (marker.unmark)(world, entities);
+            }
+        }
+
+        let mut scene = world.resource_mut::<ProjectScene>();
+        match saved {
+            Some(Ok((_, left_out))) => {
+                scene.current = Some(name.trim().to_string());
+                scene.status = (left_out > 0).then(|| {
+                    format!(
+                        "{left_out} handles to assets made in code were left out, \
+                         add those assets with a fixed id (Handle::weak_from_u128) to keep them"
+                    )
+                });
+            }
+            Some(Err(err)) => scene.status = Some(err),
+            None => {}
+        }
+        scene.name = name;
+
+        match &scene.current {
+            Some(current) => ui.label(format!("The world is {current}, hot reloads keep it.")),
+            None => ui.label("The world is the game's setup, hot reloads rebuild it."),
+        };
+        if let Some(current) = scene.current.clone() {
+            if ui
+                .button("Start with this scene")
+                .on_hover_text("instead of the game's setup, in the project's editor config")
+                .clicked()
+            {
+                let dir = std::env::current_dir().unwrap_or_default();
+                scene.status = EditorConfig::load(&dir)
+                    .and_then(|config| EditorConfig { scene: Some(current), ..config }.save(&dir))
+                    .err();
+            }
+        }
+        if let Some(status) = &scene.status {
+            ui.colored_label(ui.visuals().warn_fg_color, status);
+        }
+
+        ui.add_space(4.0);
+        egui::CollapsingHeader::new("Saved scenes").default_open(true).show(ui, |ui| {
+            let scenes = saved_scenes();
+            if scenes.is_empty() {
+                ui.weak(format!("Nothing in {SCENES_DIR} yet."));
+            }
+            for saved in scenes {
+                ui.horizontal(|ui| {
+                    ui.monospace(&saved);
+                    if ui
+                        .small_button("Open")
+                        .on_hover_text("replaces the world, unsaved changes are lost")
+                        .clicked()
+                    {
+                        scene.name = saved.clone();
+                        scene.pending = Some(saved);
+                    }
+                });
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{setup, GetRidOf};
+    use bevy::log::LogPlugin;
+    use bevy::prelude::{
+        default, App, Assets, Color, DefaultPlugins, Handle, Mesh, PluginGroup, StandardMaterial, Startup, Vec3,
+        WindowPlugin,
+    };
+    use bevy::render::settings::{RenderCreation, WgpuSettings};
+    use bevy::render::RenderPlugin;
+    use bevy::window::ExitCondition;
+    use bevy::winit::WinitPlugin;
+
+    /// The template's game without a window or a gpu, after its first frame.
+    /// `current` is the scene that took over from the setup, if any.
+    fn game(current: Option<&str>) -> App {
+        let mut app = App::new();
+        app.add_plugins(
+            DefaultPlugins
+                .set(RenderPlugin {
+                    render_creation: RenderCreation::Automatic(WgpuSettings { backends: None, ..default() }),
+                    ..default()
+                })
+                .set(WindowPlugin { primary_window: None, exit_condition: ExitCondition::DontExit, ..default() })
+                .build()
+                .disable::<WinitPlugin>()
+                .disable::<LogPlugin>(),
+        );
+        app.register_type::<GetRidOf>();
+        let mut scene = ProjectScene::new(None, vec![ResetMarker::of::<GetRidOf>()]);
+        scene.current = current.map(str::to_string);
+        app.insert_resource(scene).add_systems(Startup, setup);
+        app.update();
+        app
+    }
+
+    #[test]
+    fn the_default_scene_comes_back() {
+        let mut first_run = game(None);
+        // so transforms and visibility have been worked out
+        first_run.update();
+        let (text, _, left_out) = to_ron(&first_run.world).unwrap();
+        assert_eq!(left_out, 0);
+        for left_out in ["GlobalTransform", "ViewVisibility", "Aabb", "StrongHandle", "GetRidOf"] {
+            assert!(!text.contains(left_out), "{left_out} was saved");
+        }
+
+        let mut next_run = game(Some("main"));
+        let scene = parse(&next_run.world, &text).unwrap();
+        spawn(&mut next_run.world, &scene, "main").unwrap();
+        next_run.update();
+        let world = &mut next_run.world;
+
+        let (mesh, material, transform, global) = world
+            .query::<(&Handle<Mesh>, &Handle<StandardMaterial>, &Transform, &GlobalTransform)>()
+            .single(world);
+        assert!(world.resource::<Assets<Mesh>>().contains(mesh));
+        let material = world.resource::<Assets<StandardMaterial>>().get(material).unwrap();
+        assert_eq!(material.base_color, Color::rgb_u8(21, 14, 25));
+        assert_eq!(transform.translation, Vec3::new(0.0, 0.5, 0.0));
+        assert_eq!(global.translation(), transform.translation);
+
+        let (light, global) = world.query::<(&PointLight, &GlobalTransform)>().single(world);
+        assert!(light.shadows_enabled);
+        assert_eq!(global.translation(), Vec3::new(4.0, 8.0, 4.0));
+        world.query::<(&Camera3d, &CameraRenderGraph, &Frustum)>().single(world);
+    }
+}
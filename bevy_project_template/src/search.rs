@@ -0,0 +1,361 @@
+use crate::buffer::Buffer;
+use crate::file_tree;
+use bevy_editor_common::lsp::Position;
+use bevy_editor_pls::egui::epaint::text::cursor::CCursor;
+use bevy_editor_pls::egui::text_edit::TextEditOutput;
+use bevy_editor_pls::egui::{self, Color32, Key, Rect, RichText, ScrollArea, TextEdit, Ui};
+use crossbeam_channel::{Receiver, TryRecvError};
+use regex::{NoExpand, Regex, RegexBuilder};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Past this many matches in a file we stop counting and highlighting.
+const MAX_MATCHES: usize = 10_000;
+/// Find in Files stops after this many hits.
+const MAX_HITS: usize = 2_000;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+impl SearchOptions {
+    fn ui(&mut self, ui: &mut Ui) -> bool {
+        let regex = ui.toggle_value(&mut self.regex, ".*").on_hover_text("regular expression");
+        let case = ui.toggle_value(&mut self.case_sensitive, "Aa").on_hover_text("match case");
+        regex.changed() || case.changed()
+    }
+
+    fn build(self, query: &str) -> Result<Regex, String> {
+        let pattern = if self.regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .multi_line(true)
+            .build()
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// The char ranges of the (non empty) matches of `regex` in `text`.
+fn char_ranges(text: &str, regex: &Regex) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut byte = 0;
+    let mut char = 0;
+    for found in regex.find_iter(text).filter(|found| !found.is_empty()).take(MAX_MATCHES) {
+        char += text[byte..found.start()].chars().count();
+        let start = char;
+        char += found.as_str().chars().count();
+        byte = found.end();
+        ranges.push(start..char);
+    }
+    ranges
+}
+
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(index, _)| index)
+}
+
+/// Find and replace within the open buffer.
+#[derive(Default)]
+pub struct FindBar {
+    pub open: bool,
+    query: String,
+    replacement: String,
+    options: SearchOptions,
+    /// Which of the matches we're on.
+    current: usize,
+    focus: bool,
+}
+
+impl FindBar {
+    pub fn show(&mut self) {
+        self.open = true;
+        self.focus = true;
+    }
+
+    fn regex(&self) -> Option<Result<Regex, String>> {
+        (!self.query.is_empty()).then(|| self.options.build(&self.query))
+    }
+
+    fn select(&mut self, buffer: &mut Buffer, matches: &[Range<usize>], index: usize) {
+        if matches.is_empty() {
+            return;
+        }
+        self.current = index % matches.len();
+        buffer.jump_to = Some(matches[self.current].clone());
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, buffer: &mut Buffer) {
+        if !self.open {
+            return;
+        }
+        let regex = self.regex();
+        let matches = match &regex {
+            Some(Ok(regex)) => char_ranges(&buffer.text, regex),
+            _ => Vec::new(),
+        };
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let query = ui.add(TextEdit::singleline(&mut self.query).hint_text("find"));
+            if std::mem::take(&mut self.focus) {
+                query.request_focus();
+            }
+            changed |= query.changed();
+            changed |= self.options.ui(ui);
+            let enter = query.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter));
+            let backwards = ui.input(|input| input.modifiers.shift);
+            match &regex {
+                Some(Err(err)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, "invalid regex")
+                        .on_hover_text(err);
+                }
+                Some(Ok(_)) if matches.is_empty() => {
+                    ui.label("no results");
+                }
+                Some(Ok(_)) => {
+                    let count = if matches.len() == MAX_MATCHES {
+                        format!("{}+", MAX_MATCHES)
+                    } else {
+                        matches.len().to_string()
+                    };
+                    ui.label(format!("{} of {count}", self.current.min(matches.len() - 1) + 1));
+                }
+                None => {}
+            }
+            let previous = ui.small_button(egui_phosphor::regular::ARROW_UP).on_hover_text("previous match");
+            let next = ui.small_button(egui_phosphor::regular::ARROW_DOWN).on_hover_text("next match");
+            if previous.clicked() || (enter && backwards) {
+                let index = self.current + matches.len().max(1) - 1;
+                self.select(buffer, &matches, index);
+                query.request_focus();
+            } else if next.clicked() || enter {
+                self.select(buffer, &matches, self.current + 1);
+                query.request_focus();
+            }
+            if ui.small_button(egui_phosphor::regular::X).clicked() {
+                self.open = false;
+            }
+        });
+        if changed {
+            self.current = 0;
+            self.select(buffer, &matches, 0);
+        }
+
+        let regex = regex.and_then(Result::ok);
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.replacement).hint_text("replace"))
+                .on_hover_text(if self.options.regex {
+                    "$1 or ${name} insert capture groups"
+                } else {
+                    "inserted as is"
+                });
+            let Some(regex) = regex.filter(|_| !matches.is_empty() && !buffer.read_only) else {
+                ui.add_enabled(false, egui::Button::new("replace"));
+                ui.add_enabled(false, egui::Button::new("replace all"));
+                return;
+            };
+            if ui.button("replace").clicked() {
+                self.replace_current(buffer, &regex, &matches);
+            }
+            if ui.button("replace all").clicked() {
+                buffer.text = if self.options.regex {
+                    regex.replace_all(&buffer.text, self.replacement.as_str()).into_owned()
+                } else {
+                    regex.replace_all(&buffer.text, NoExpand(&self.replacement)).into_owned()
+                };
+                self.current = 0;
+            }
+        });
+    }
+
+    fn replace_current(&mut self, buffer: &mut Buffer, regex: &Regex, matches: &[Range<usize>]) {
+        let current = self.current.min(matches.len() - 1);
+        let range = matches[current].clone();
+        let start = byte_index(&buffer.text, range.start);
+        let end = byte_index(&buffer.text, range.end);
+        let mut replacement = String::new();
+        match regex.captures_at(&buffer.text, start) {
+            Some(captures) if self.options.regex => captures.expand(&self.replacement, &mut replacement),
+            _ => replacement.push_str(&self.replacement),
+        }
+        buffer.text.replace_range(start..end, &replacement);
+        // move on to whatever comes after what we just put in
+        let after = range.start + replacement.chars().count();
+        let matches = char_ranges(&buffer.text, regex);
+        let next = matches.iter().position(|found| found.start >= after).unwrap_or(0);
+        self.select(buffer, &matches, next);
+    }
+
+    /// Highlights the matches in the editor that was just drawn into `output`.
+    pub fn paint(&self, ui: &Ui, text: &str, output: &TextEditOutput) {
+        let Some(Ok(regex)) = self.open.then(|| self.regex()).flatten() else {
+            return;
+        };
+        let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+        let galley = &output.galley;
+        let offset = output.galley_pos.to_vec2();
+        let matches = char_ranges(text, &regex);
+        let current = self.current.min(matches.len().saturating_sub(1));
+        for (index, found) in matches.iter().enumerate() {
+            let start = galley.from_ccursor(CCursor::new(found.start));
+            let end = galley.from_ccursor(CCursor::new(found.end));
+            let start_rect = galley.pos_from_cursor(&start).translate(offset);
+            let right = if end.rcursor.row == start.rcursor.row {
+                galley.pos_from_cursor(&end).translate(offset).left()
+            } else {
+                galley.rows[start.rcursor.row].rect.right() + offset.x
+            };
+            let rect = Rect::from_x_y_ranges(start_rect.left()..=right, start_rect.y_range());
+            if !output.text_clip_rect.intersects(rect) {
+                continue;
+            }
+            let color = if index == current {
+                Color32::from_rgba_unmultiplied(255, 200, 0, 90)
+            } else {
+                Color32::from_rgba_unmultiplied(255, 200, 0, 35)
+            };
+            painter.rect_filled(rect, 2.0, color);
+        }
+    }
+}
+
+/// A line matching a Find in Files search.
+pub struct Hit {
+    pub path: PathBuf,
+    pub position: Position,
+    line: String,
+}
+
+/// Searches every project file line by line on a background thread, like
+/// ripgrep does: gitignored files and `target` are skipped, so are files that
+/// aren't UTF-8.
+#[derive(Default)]
+pub struct FindInFiles {
+    query: String,
+    options: SearchOptions,
+    hits: Vec<Hit>,
+    /// The running search, dropped once it's done.
+    searching: Option<Receiver<Hit>>,
+    searched: bool,
+    error: Option<String>,
+}
+
+impl FindInFiles {
+    fn search(&mut self, root: &Path) {
+        self.hits.clear();
+        self.searching = None;
+        self.searched = false;
+        self.error = None;
+        if self.query.is_empty() {
+            return;
+        }
+        let regex = match self.options.build(&self.query) {
+            Ok(regex) => regex,
+            Err(err) => {
+                self.error = Some(err);
+                return;
+            }
+        };
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let root = root.to_path_buf();
+        std::thread::spawn(move || {
+            let files = file_tree::walk(&root)
+                .flatten()
+                .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file()));
+            let mut sent = 0;
+            for entry in files {
+                let Ok(text) = std::fs::read_to_string(entry.path()) else {
+                    continue;
+                };
+                for (line_number, line) in text.lines().enumerate() {
+                    let Some(found) = regex.find(line) else {
+                        continue;
+                    };
+                    let hit = Hit {
+                        path: entry.path().to_path_buf(),
+                        position: Position {
+                            line: line_number as u32,
+                            character: line[..found.start()].encode_utf16().count() as u32,
+                        },
+                        line: line.trim().to_string(),
+                    };
+                    sent += 1;
+                    // a new search started if nobody's listening anymore
+                    if sent > MAX_HITS || tx.send(hit).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        self.searching = Some(rx);
+        self.searched = true;
+    }
+
+    /// Returns the hit that was clicked.
+    pub fn ui(&mut self, ui: &mut Ui, root: &Path) -> Option<(PathBuf, Position)> {
+        if let Some(searching) = &self.searching {
+            loop {
+                match searching.try_recv() {
+                    Ok(hit) => self.hits.push(hit),
+                    Err(TryRecvError::Empty) => {
+                        ui.ctx().request_repaint();
+                        break;
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                        self.searching = None;
+                        break;
+                    }
+                }
+            }
+        }
+        let mut clicked = None;
+        egui::CollapsingHeader::new("Find in Files")
+            .id_source("code editor find in files")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let query = ui.add(TextEdit::singleline(&mut self.query).hint_text("search the project"));
+                    let mut search = query.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter));
+                    search |= self.options.ui(ui);
+                    search |= ui.button(egui_phosphor::regular::MAGNIFYING_GLASS).clicked();
+                    if search {
+                        self.search(root);
+                    }
+                });
+                if let Some(err) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                    return;
+                }
+                if self.searching.is_some() {
+                    ui.weak(format!("searching... {} results", self.hits.len()));
+                } else if self.searched {
+                    let more = if self.hits.len() >= MAX_HITS { "+" } else { "" };
+                    ui.weak(format!("{}{more} results", self.hits.len()));
+                }
+                ScrollArea::vertical()
+                    .id_source("find in files results")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        let mut last_path = None;
+                        for hit in &self.hits {
+                            if last_path != Some(&hit.path) {
+                                let name = hit.path.strip_prefix(root).unwrap_or(&hit.path);
+                                ui.label(RichText::new(name.display().to_string()).strong());
+                                last_path = Some(&hit.path);
+                            }
+                            let text = format!("{:>5}  {}", hit.position.line + 1, hit.line);
+                            if ui.selectable_label(false, RichText::new(text).monospace()).clicked() {
+                                clicked = Some((hit.path.clone(), hit.position));
+                            }
+                        }
+                    });
+            });
+        clicked
+    }
+}
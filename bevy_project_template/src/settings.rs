@@ -0,0 +1,56 @@
+use crate::keybindings::{EditorAction, KeyBinding};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Editor preferences that follow the user from project to project, stored
+/// next to the project manager's own data.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct EditorSettings {
+    pub theme: String,
+    /// Rust and TOML files go through their formatter before being saved.
+    pub format_on_save: bool,
+    /// Only the shortcuts that differ from the defaults.
+    pub keybindings: BTreeMap<EditorAction, KeyBinding>,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        EditorSettings {
+            theme: "Gruvbox".to_string(),
+            format_on_save: false,
+            keybindings: BTreeMap::new(),
+        }
+    }
+}
+
+impl EditorSettings {
+    fn path() -> Option<PathBuf> {
+        let dirs = ProjectDirs::from("com", "malek", "bevy_editor")?;
+        Some(dirs.config_dir().join("editor_settings.ron"))
+    }
+
+    /// Missing or broken settings just mean the defaults.
+    pub fn load() -> EditorSettings {
+        let Some(path) = Self::path() else {
+            return EditorSettings::default();
+        };
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| ron::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("no config directory for this user")?;
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        std::fs::write(&path, text)
+            .map_err(|err| format!("couldn't save the settings to {}: {err}", path.display()))
+    }
+}
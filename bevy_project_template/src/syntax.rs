@@ -0,0 +1,125 @@
+use egui_code_editor::{ColorTheme, Syntax};
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Language {
+    Rust,
+    Toml,
+    Ron,
+    Wgsl,
+    Glsl,
+    Json,
+    Plain,
+}
+
+impl Language {
+    pub const ALL: [Language; 7] = [
+        Language::Rust,
+        Language::Toml,
+        Language::Ron,
+        Language::Wgsl,
+        Language::Glsl,
+        Language::Json,
+        Language::Plain,
+    ];
+
+    pub fn from_path(path: &Path) -> Language {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("rs") => Language::Rust,
+            // Cargo.lock is toml too
+            Some("toml" | "lock") => Language::Toml,
+            Some("ron") => Language::Ron,
+            Some("wgsl") => Language::Wgsl,
+            Some("glsl" | "vert" | "frag" | "comp" | "geom") => Language::Glsl,
+            Some("json") => Language::Json,
+            _ => Language::Plain,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Rust => "Rust",
+            Language::Toml => "TOML",
+            Language::Ron => "RON",
+            Language::Wgsl => "WGSL",
+            Language::Glsl => "GLSL",
+            Language::Json => "JSON",
+            Language::Plain => "Plain text",
+        }
+    }
+
+    pub fn syntax(self) -> Syntax {
+        match self {
+            Language::Rust => Syntax::rust(),
+            Language::Toml => Syntax::new("toml")
+                .with_comment("#")
+                .with_keywords(["true", "false"]),
+            Language::Ron => Syntax::new("ron")
+                .with_comment("//")
+                .with_comment_multiline(["/*", "*/"])
+                .with_keywords(["true", "false"])
+                .with_special(["Some", "None"]),
+            Language::Wgsl => Syntax::new("wgsl")
+                .with_comment("//")
+                .with_comment_multiline(["/*", "*/"])
+                .with_keywords([
+                    "alias", "break", "case", "const", "const_assert", "continue", "continuing",
+                    "default", "diagnostic", "discard", "else", "enable", "false", "fn", "for",
+                    "if", "let", "loop", "override", "requires", "return", "struct", "switch",
+                    "true", "var", "while",
+                ])
+                .with_types([
+                    "bool", "f16", "f32", "i32", "u32", "vec2", "vec3", "vec4", "vec2f",
+                    "vec3f", "vec4f", "vec2i", "vec3i", "vec4i", "vec2u", "vec3u", "vec4u",
+                    "mat2x2", "mat3x3", "mat4x4", "mat2x2f", "mat3x3f", "mat4x4f", "array",
+                    "atomic", "ptr", "sampler", "sampler_comparison", "texture_2d",
+                    "texture_3d", "texture_cube", "texture_2d_array", "texture_storage_2d",
+                    "texture_depth_2d",
+                ])
+                .with_special([
+                    "vertex", "fragment", "compute", "group", "binding", "location", "builtin",
+                    "workgroup_size", "import", "define_import_path",
+                ]),
+            Language::Glsl => Syntax::new("glsl")
+                .with_comment("//")
+                .with_comment_multiline(["/*", "*/"])
+                .with_keywords([
+                    "attribute", "break", "buffer", "case", "const", "continue", "default",
+                    "discard", "do", "else", "false", "flat", "for", "highp", "if", "in",
+                    "inout", "layout", "lowp", "mediump", "out", "precision", "return",
+                    "smooth", "struct", "switch", "true", "uniform", "varying", "while",
+                ])
+                .with_types([
+                    "bool", "float", "double", "int", "uint", "void", "vec2", "vec3", "vec4",
+                    "ivec2", "ivec3", "ivec4", "uvec2", "uvec3", "uvec4", "bvec2", "bvec3",
+                    "bvec4", "mat2", "mat3", "mat4", "sampler2D", "sampler3D", "samplerCube",
+                ])
+                .with_special(["version", "define", "ifdef", "ifndef", "endif", "include"]),
+            Language::Json => Syntax::new("json").with_keywords(["true", "false", "null"]),
+            Language::Plain => Syntax::new("text"),
+        }
+    }
+}
+
+pub const THEMES: [(&str, ColorTheme); 9] = [
+    ("Gruvbox", ColorTheme::GRUVBOX),
+    ("Gruvbox dark", ColorTheme::GRUVBOX_DARK),
+    ("Gruvbox light", ColorTheme::GRUVBOX_LIGHT),
+    ("Ayu", ColorTheme::AYU),
+    ("Ayu mirage", ColorTheme::AYU_MIRAGE),
+    ("Ayu dark", ColorTheme::AYU_DARK),
+    ("GitHub dark", ColorTheme::GITHUB_DARK),
+    ("GitHub light", ColorTheme::GITHUB_LIGHT),
+    ("Sonokai", ColorTheme::SONOKAI),
+];
+
+/// Falls back to gruvbox for names we don't know (anymore).
+pub fn theme(name: &str) -> ColorTheme {
+    THEMES
+        .iter()
+        .find(|(theme, _)| *theme == name)
+        .map_or(ColorTheme::GRUVBOX, |(_, theme)| *theme)
+}
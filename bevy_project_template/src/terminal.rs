@@ -1,151 +1,198 @@
-use bevy_editor_pls::egui::{Color32, RichText, ScrollArea, Ui};
-use cansi::v3::categorise_text;
+use bevy::app::AppExit;
+use bevy::prelude::{EventWriter, Res, Resource};
+use bevy_editor_common::process::OutputReceiver;
+use bevy_editor_common::protocol::{
+    BuildEvent, EditorToWatcher, WatcherCommand, WatcherToEditor, RX_PORT, TX_PORT,
+};
 use crossbeam_channel::{Receiver, Sender};
-use std::io::Read;
-use std::io::Write;
-use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-
-pub fn rich_text_display_multiline(ui: &mut Ui, rich_texts: &[RichText]) {
-    let mut amount = 0;
-    for _ in 0..rich_texts.len() {
-        let mut reached_the_end = true;
-        ui.horizontal(|ui| {
-            for (i, rich_text) in rich_texts.iter().enumerate() {
-                if i <= amount {
-                    continue;
-                }
-                ui.label(rich_text.clone());
-                if rich_text.text().contains('\n') {
-                    amount = i;
-                    reached_the_end = false;
-                    break;
-                }
-            }
-        });
-        if reached_the_end {
-            break;
-        }
-    }
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectionStatus {
+    /// `RX_PORT`/`TX_PORT` aren't set, the game wasn't started by the watcher.
+    Standalone,
+    Connecting,
+    Connected,
+    /// The watcher went away, we keep trying to get it back.
+    Disconnected(String),
 }
 
-pub fn from_cansi_to_egui_color(color: cansi::Color) -> Color32 {
-    match color {
-        cansi::Color::Black => Color32::BLACK,
-        cansi::Color::Red => Color32::RED,
-        cansi::Color::Green => Color32::GREEN,
-        cansi::Color::Yellow => Color32::YELLOW,
-        cansi::Color::Blue => Color32::BLUE,
-        cansi::Color::Magenta => Color32::from_rgb(255, 0, 255),
-        cansi::Color::Cyan => Color32::LIGHT_BLUE,
-        cansi::Color::White => Color32::WHITE,
-        cansi::Color::BrightBlack => Color32::BLACK,
-        cansi::Color::BrightRed => Color32::LIGHT_RED,
-        cansi::Color::BrightGreen => Color32::LIGHT_GREEN,
-        cansi::Color::BrightYellow => Color32::LIGHT_YELLOW,
-        cansi::Color::BrightBlue => Color32::LIGHT_BLUE,
-        cansi::Color::BrightMagenta => Color32::from_rgb(255, 30, 255),
-        cansi::Color::BrightCyan => Color32::LIGHT_BLUE,
-        cansi::Color::BrightWhite => Color32::WHITE,
-    }
+/// The game's side of the link to the hotreload watcher. Connecting happens on a
+/// background thread, so creating one never blocks a frame, and whenever the
+/// connection drops it's re-established (the watcher replays the output we missed).
+///
+/// There's one per app, as a resource, since the watcher only streams the output
+/// to a single connection.
+#[derive(Resource)]
+pub struct WatcherConnection {
+    output: OutputReceiver,
+    builds: Receiver<(BuildEvent, SystemTime)>,
+    to_watcher: Sender<EditorToWatcher>,
+    status: Arc<Mutex<ConnectionStatus>>,
+    queued: AtomicUsize,
+    /// How many messages made it onto the socket.
+    written: Arc<AtomicUsize>,
+    exiting: AtomicBool,
 }
 
-pub fn rich_text_vec(string: &str) -> Vec<RichText> {
-    let mut rich_texts = vec![];
-    let text = categorise_text(string);
-    for t in text.iter() {
-        let mut rich_text = RichText::new(t.text);
-        if let Some(fg) = t.fg {
-            rich_text = rich_text.color(from_cansi_to_egui_color(fg));
-        }
-        if let Some(italics) = t.italic {
-            if italics {
-                rich_text = rich_text.italics();
-            }
+impl Default for WatcherConnection {
+    fn default() -> Self {
+        let (output_tx, output) = crossbeam_channel::unbounded();
+        let (builds_tx, builds) = crossbeam_channel::unbounded();
+        let (to_watcher, to_watcher_rx) = crossbeam_channel::unbounded();
+        let status = Arc::new(Mutex::new(ConnectionStatus::Standalone));
+        let written = Arc::new(AtomicUsize::new(0));
+        if let (Some(rx_port), Some(tx_port)) = (port(RX_PORT), port(TX_PORT)) {
+            *status.lock().unwrap() = ConnectionStatus::Connecting;
+            let status = status.clone();
+            let written = written.clone();
+            thread::spawn(move || {
+                connection_thread(rx_port, tx_port, output_tx, builds_tx, to_watcher_rx, status, written)
+            });
         }
-        if let Some(bg) = t.bg {
-            rich_text = rich_text.background_color(from_cansi_to_egui_color(bg));
+        WatcherConnection {
+            output,
+            builds,
+            to_watcher,
+            status,
+            queued: AtomicUsize::new(0),
+            written,
+            exiting: AtomicBool::new(false),
         }
-        rich_texts.push(rich_text);
     }
-    rich_texts
 }
 
-fn create_tcp_listener(port: u16) -> TcpStream {
-    match TcpListener::bind(format!("localhost:{}", port)) {
-        Ok(listener) => {
-            println!("bound to: {port}");
-            let (listener, _) = listener.accept().unwrap();
-            return listener;
-        },
-        Err(err) => {
-            panic!("{err}")
-        }
+impl WatcherConnection {
+    pub fn output(&self) -> &OutputReceiver {
+        &self.output
     }
-}
 
-fn create_tcp_stream(port: u16) -> TcpStream {
-    for i in 0..50 {
-        match TcpStream::connect(format!("localhost:{port}")) {
-            Ok(stream) => {
-                println!("connected to: {port}");
-                return stream;
-            },
-            Err(err) => {
-                if i >= 10 {
-                    eprintln!("{err}");
-                }
-                thread::sleep(Duration::from_secs(1));
-            }
+    /// The watcher's build events, with when they happened.
+    pub fn builds(&self) -> &Receiver<(BuildEvent, SystemTime)> {
+        &self.builds
+    }
+
+    pub fn status(&self) -> ConnectionStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Queued while we're not connected, sent once we are.
+    pub fn send(&self, message: EditorToWatcher) {
+        if self.to_watcher.send(message).is_ok() {
+            self.queued.fetch_add(1, Ordering::SeqCst);
         }
     }
-    panic!("ran out of iterations no tcp connection to game working");
-}
 
-pub fn setup_streams() -> (Receiver<u8>, Sender<u8>) {
-    let stream = create_tcp_stream(std::env::var("RX_PORT").unwrap().parse().unwrap());
-    let mut listener = create_tcp_listener(std::env::var("TX_PORT").unwrap().parse().unwrap());
+    /// Whether everything passed to [`Self::send`] has been written to the watcher.
+    pub fn flushed(&self) -> bool {
+        self.written.load(Ordering::SeqCst) >= self.queued.load(Ordering::SeqCst)
+    }
 
+    /// Sends `command`, and if the watcher needs us gone for it, exits once it's
+    /// been written (see [`exit_when_flushed`]).
+    pub fn command(&self, command: WatcherCommand) {
+        self.send(EditorToWatcher::Command(command));
+        if command.needs_exit() {
+            self.exiting.store(true, Ordering::SeqCst);
+        }
+    }
 
-    let (terminal_output_tx, mut terminal_output_rx) = crossbeam_channel::unbounded();
+    pub fn is_exiting(&self) -> bool {
+        self.exiting.load(Ordering::SeqCst)
+    }
+}
 
-    let (quit_reason_tx, quit_reason_rx) = crossbeam_channel::unbounded();
+pub fn exit_when_flushed(connection: Res<WatcherConnection>, mut app_exit: EventWriter<AppExit>) {
+    if connection.is_exiting() && connection.flushed() {
+        app_exit.send(AppExit);
+    }
+}
 
-    thread::spawn(move || {
-        for b in stream.bytes() {
-            if let Ok(b) = b {
-                terminal_output_tx.send(b).unwrap();
-            }
-        }
-    });
+fn port(var: &str) -> Option<u16> {
+    std::env::var(var).ok()?.parse().ok()
+}
 
-    thread::spawn(move || {
-        let b = quit_reason_rx.recv().unwrap();
-        listener.write(&[b]).expect("TODO: panic message");
-        listener.flush().expect("TODO: panic message");
-    });
-    (terminal_output_rx, quit_reason_tx)
+fn connect(port: u16) -> std::io::Result<TcpStream> {
+    TcpStream::connect(format!("localhost:{port}"))
 }
 
-pub fn display_terminal(
-    terminal_string: &mut String,
-    terminal_output: Receiver<u8>,
-    ui: &mut Ui,
-    scroll_to_bottom: bool,
+fn connection_thread(
+    rx_port: u16,
+    tx_port: u16,
+    output_tx: Sender<Vec<u8>>,
+    builds_tx: Sender<(BuildEvent, SystemTime)>,
+    to_watcher_rx: Receiver<EditorToWatcher>,
+    status: Arc<Mutex<ConnectionStatus>>,
+    written: Arc<AtomicUsize>,
 ) {
-    for _ in 0..terminal_output.len() + 20 {
-        for byte in terminal_output.try_recv() {
-            //print!("{}", char::from(byte));
-            terminal_string.push(char::from(byte));
+    // taken off `to_watcher_rx` but not written yet, it survives reconnecting
+    let mut unsent: Option<EditorToWatcher> = None;
+    loop {
+        let streams = connect(rx_port).and_then(|output| Ok((output, connect(tx_port)?)));
+        let (output, mut messages) = match streams {
+            Ok(streams) => streams,
+            Err(err) => {
+                {
+                    let mut status = status.lock().unwrap();
+                    if *status != ConnectionStatus::Connecting {
+                        *status = ConnectionStatus::Disconnected(err.to_string());
+                    }
+                }
+                thread::sleep(Duration::from_secs(1));
+                continue;
+            }
+        };
+        *status.lock().unwrap() = ConnectionStatus::Connected;
+        // the watcher starts every connection with everything it has, don't show it twice
+        if output_tx.send(b"\x1bc".to_vec()).is_err() {
+            return;
         }
+
+        // the output stream forwards into a channel of its own so we notice when it closes
+        let (from_watcher_tx, from_watcher_rx) = crossbeam_channel::unbounded();
+        read_watcher_messages(output, from_watcher_tx);
+        let reason = loop {
+            if let Some(message) = unsent.take() {
+                if let Err(err) = message.write_to(&mut messages) {
+                    unsent = Some(message);
+                    break err.to_string();
+                }
+                written.fetch_add(1, Ordering::SeqCst);
+            }
+            crossbeam_channel::select! {
+                recv(from_watcher_rx) -> message => match message {
+                    Ok(WatcherToEditor::Output(chunk)) => {
+                        if output_tx.send(chunk).is_err() {
+                            // nobody is displaying us anymore
+                            return;
+                        }
+                    }
+                    Ok(WatcherToEditor::Build { event, at }) => {
+                        builds_tx.send((event, UNIX_EPOCH + Duration::from_millis(at))).ok();
+                    }
+                    Err(_) => break "the watcher closed the connection".to_string(),
+                },
+                recv(to_watcher_rx) -> message => match message {
+                    Ok(message) => unsent = Some(message),
+                    Err(_) => return,
+                },
+            }
+        };
+        *status.lock().unwrap() = ConnectionStatus::Disconnected(reason);
+        thread::sleep(Duration::from_secs(1));
     }
-    ScrollArea::new(true).show(ui, |ui| {
-        rich_text_display_multiline(ui, &rich_text_vec(terminal_string.as_str()));
-        let rect = ui.label("").rect;
-        if scroll_to_bottom {
-            ui.scroll_to_rect(rect, None);
+}
+
+fn read_watcher_messages(mut stream: TcpStream, tx: Sender<WatcherToEditor>) {
+    thread::spawn(move || {
+        while let Ok(message) = WatcherToEditor::read_from(&mut stream) {
+            if tx.send(message).is_err() {
+                return;
+            }
         }
     });
 }
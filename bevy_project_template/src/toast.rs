@@ -0,0 +1,77 @@
+use bevy_editor_pls::egui::{self, Align2, Color32, Frame, Id, Order, RichText, Ui};
+
+/// How long a notification stays up, in seconds.
+const SHOW_FOR: f64 = 8.0;
+
+struct Toast {
+    message: String,
+    error: bool,
+    /// egui time it was posted at.
+    at: Option<f64>,
+}
+
+/// Messages stacked in the corner of a window that go away on their own,
+/// for things worth knowing that shouldn't get in the way.
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.toasts.push(Toast {
+            message: message.into(),
+            error: true,
+            at: None,
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.toasts.push(Toast {
+            message: message.into(),
+            error: false,
+            at: None,
+        });
+    }
+
+    /// Shows them over the bottom right corner of `ui`.
+    pub fn show(&mut self, ui: &Ui, id: impl std::hash::Hash) {
+        let now = ui.input(|input| input.time);
+        for toast in &mut self.toasts {
+            toast.at.get_or_insert(now);
+        }
+        self.toasts
+            .retain(|toast| toast.at.is_some_and(|at| now - at < SHOW_FOR));
+        if self.toasts.is_empty() {
+            return;
+        }
+        ui.ctx().request_repaint_after(std::time::Duration::from_millis(500));
+        let corner = ui.max_rect().right_bottom();
+        let mut dismissed = None;
+        egui::Area::new(Id::new(id))
+            .order(Order::Foreground)
+            .pivot(Align2::RIGHT_BOTTOM)
+            .fixed_pos(corner - egui::vec2(8.0, 8.0))
+            .show(ui.ctx(), |ui| {
+                for (index, toast) in self.toasts.iter().enumerate() {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(400.0);
+                        ui.horizontal_top(|ui| {
+                            let color = if toast.error {
+                                ui.visuals().error_fg_color
+                            } else {
+                                Color32::PLACEHOLDER
+                            };
+                            ui.label(RichText::new(&toast.message).color(color));
+                            if ui.small_button(egui_phosphor::regular::X).clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(index) = dismissed {
+            self.toasts.remove(index);
+        }
+    }
+}
@@ -3,12 +3,18 @@
 // I like experimenting. See how it turns out for yourself.
 
 use std::env::args;
-use crossbeam_channel::Receiver;
+use bevy_editor_common::build::BuildTracker;
+use bevy_editor_common::process::{command_channels, OutputReceiver};
+use bevy_editor_common::protocol::{BuildEvent, EditorToWatcher, FailureReport, WatcherCommand, WatcherToEditor, FAILURE_REPORT, RX_PORT, TX_PORT};
+use crossbeam_channel::{Receiver, Sender};
 use std::io::{Read, Write};
-use std::net::{SocketAddr, TcpListener, TcpStream};
-use std::process::{Child, Command, Stdio};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 fn main() {
     // only ui only does ui.
@@ -20,131 +26,297 @@ fn main() {
         }
     }
     let location_to_run = position.unwrap();
+    let mut failure_report = None;
+    let child_stdin = SharedStdin::default();
+    spawn_own_stdin_thread(child_stdin.clone());
+    // bound once, every run of the game connects to the same ports
+    let (tx_port, rx_port) = ( portpicker::pick_unused_port_range(1026..10_000).expect("No free tcp port"), portpicker::pick_unused_port_range(1026..10_000).expect("No free tcp port"));
+    let tx_listener = TcpListener::bind(format!("localhost:{tx_port}")).unwrap();
+    let rx_listener = TcpListener::bind(format!("localhost:{rx_port}")).unwrap();
+    let editor_output = EditorOutput::default();
+    spawn_editor_output_thread(tx_listener, editor_output.clone());
+    let quit_commands = spawn_editor_message_thread(rx_listener, child_stdin.clone(), PathBuf::from(&location_to_run));
     loop {
-        let (tx_port, rx_port) = ( portpicker::pick_unused_port_range(1026..10_000).expect("No free tcp port"), portpicker::pick_unused_port_range(1026..10_000).expect("No free tcp port"));
-        let child_command = create_child_process(only_ui, location_to_run.clone(), rx_port, tx_port);
-        let (rx, child) = spawn_child_with_std_out_err_channel(child_command);
-        let tx_editor = create_tcp_listener(tx_port);
-        let rx_editor = create_tcp_stream(rx_port);
-        if let QuitType::Clean = run_child_loop(&mut only_ui, tx_editor, rx_editor, rx, child) {
-            return;
+        let child_command = create_child_process(only_ui, failure_report.take(), location_to_run.clone(), rx_port, tx_port);
+        let (rx, mut child) = command_channels(child_command, true).unwrap();
+        child_stdin.lock().unwrap().replace(child.stdin.take().unwrap());
+        editor_output.restart();
+        // whatever the last run asked for has been dealt with
+        quit_commands.drain();
+        match run_child_loop(&mut only_ui, editor_output.clone(), &quit_commands, rx, child) {
+            QuitType::Clean => return,
+            QuitType::Unclean => continue,
+            QuitType::Failed(status) => {
+                let report = FailureReport { status, output: editor_output.backlog() };
+                failure_report = write_failure_report(Path::new(&location_to_run), &report);
+            }
         }
     }
 }
 
 fn run_child_loop(
     only_ui: &mut bool,
-    mut tx_editor: TcpStream,
-    mut rx_editor: TcpStream,
-    rx: Receiver<u8>,
+    editor_output: EditorOutput,
+    quit_commands: &QuitCommands,
+    rx: OutputReceiver,
     mut child: Child,
 ) -> QuitType {
-    loop {
-        while let Ok(byte) = rx.recv() {
-            if let Err(_) = tx_editor.write(&[byte]) {
-                break;
-            }
+    let mut builds = BuildTracker::default();
+    while let Ok(chunk) = rx.recv() {
+        editor_output.write(&chunk);
+        for event in builds.feed(&chunk) {
+            editor_output.build(event);
+        }
+    }
+    if let Some(event) = builds.exited() {
+        editor_output.build(event);
+    }
+    let Ok(status) = child.wait() else {
+        return QuitType::Unclean;
+    };
+    // the game tells us what it wants right before exiting
+    match quit_commands.last() {
+        Some(WatcherCommand::Restart) => {
+            *only_ui = false;
+            return QuitType::Unclean;
         }
-        let Ok(maybe_status) = child.try_wait() else {
-            break;
-        };
-        let Some(status) = maybe_status else { break };
-        return if status.success() {
-            QuitType::Clean
-        } else {
+        Some(WatcherCommand::RestartOnlyUi) => {
             *only_ui = true;
-            QuitType::Unclean
-        };
+            return QuitType::Unclean;
+        }
+        Some(WatcherCommand::Stop) => return QuitType::Clean,
+        Some(WatcherCommand::Rebuild) | None => {}
     }
-    let mut turn_off_only_ui = [0];
-    rx_editor.read(&mut turn_off_only_ui).unwrap();
-    if turn_off_only_ui[0] == 1 {
-        *only_ui = false;
+    if status.success() {
+        return QuitType::Clean;
     }
-    return QuitType::Unclean;
+    *only_ui = true;
+    QuitType::Failed(status.to_string())
 }
 
-enum QuitType {
-    Clean,
-    Unclean,
+// the ONLY_UI run that comes next shows this to explain what happened
+fn write_failure_report(project_dir: &Path, report: &FailureReport) -> Option<PathBuf> {
+    let target = project_dir.join("target");
+    let path = target.join("bevy_editor_failure.report");
+    let written = std::fs::create_dir_all(&target)
+        .and_then(|_| std::fs::File::create(&path))
+        .and_then(|mut file| report.write_to(&mut file));
+    if let Err(err) = written {
+        eprintln!("couldn't write the failure report to {}: {err}", path.display());
+        return None;
+    }
+    Some(path)
 }
 
-fn create_tcp_listener(port: u16) -> TcpStream {
-    match TcpListener::bind(format!("localhost:{}", port)) {
-        Ok(listener) => {
-            println!("bound to: {port}");
-            let (tx_editor, _) = listener.accept().unwrap();
-            tx_editor
-        },
-        Err(err) => {
-            panic!("{err}")
+// how much of the child's output we keep around for editors that connect late
+const BACKLOG_LIMIT: usize = 4 * 1024 * 1024;
+
+// the game's editor can (re)connect at any point, it gets everything it missed
+#[derive(Clone, Default)]
+struct EditorOutput {
+    inner: Arc<Mutex<EditorOutputInner>>,
+}
+
+#[derive(Default)]
+struct EditorOutputInner {
+    backlog: Vec<u8>,
+    // the last build's start and how it ended, that's all a late editor needs
+    builds: Vec<WatcherToEditor>,
+    stream: Option<TcpStream>,
+}
+
+impl EditorOutputInner {
+    fn send(&mut self, message: &WatcherToEditor) {
+        let Some(connected) = self.stream.as_mut() else { return };
+        if message.write_to(connected).is_err() {
+            self.stream.take();
         }
     }
 }
 
-fn create_tcp_stream(port: u16) -> TcpStream {
-    for i in 0..500 {
-        match TcpStream::connect(format!("localhost:{port}")) {
-            Ok(stream) => {
-                println!("connected to: {port}");
-                return stream;
-            },
-            Err(err) => {
-                if i >= 50 {
-                    eprintln!("{err}");
-                }
-                thread::sleep(Duration::from_secs(1));
-            }
+impl EditorOutput {
+    fn write(&self, bytes: &[u8]) {
+        let mut inner = self.inner.lock().unwrap();
+        let backlog = &mut inner.backlog;
+        backlog.extend_from_slice(bytes);
+        if backlog.len() > BACKLOG_LIMIT {
+            backlog.drain(..backlog.len() - BACKLOG_LIMIT);
+        }
+        inner.send(&WatcherToEditor::Output(bytes.to_vec()));
+    }
+
+    fn build(&self, event: BuildEvent) {
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        let message = WatcherToEditor::Build { event, at };
+        let mut inner = self.inner.lock().unwrap();
+        if event == BuildEvent::Started {
+            inner.builds.clear();
+        }
+        inner.builds.push(message.clone());
+        inner.send(&message);
+    }
+
+    // a new run of the game starts from nothing, and connects again
+    fn restart(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.backlog.clear();
+        inner.builds.clear();
+        inner.stream.take();
+    }
+
+    fn backlog(&self) -> Vec<u8> {
+        self.inner.lock().unwrap().backlog.clone()
+    }
+
+    fn connect(&self, stream: TcpStream) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.stream.replace(stream);
+        let catch_up = std::iter::once(WatcherToEditor::Output(inner.backlog.clone()))
+            .chain(inner.builds.clone())
+            .collect::<Vec<_>>();
+        for message in &catch_up {
+            inner.send(message);
         }
     }
-    panic!("ran out of iterations no tcp connection to game working");
 }
 
-fn create_child_process(only_ui: bool, location_to_run: String, rx_port: u16, tx_port: u16) -> Command {
-    let mut command = std::process::Command::new("dexterous_developer_cli");
-    command.arg("run");
-    command.env("TX_PORT", rx_port.to_string());
-    command.env("RX_PORT", tx_port.to_string());
-    command.current_dir(location_to_run);
+fn spawn_editor_output_thread(listener: TcpListener, editor_output: EditorOutput) {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            editor_output.connect(stream);
+        }
+    });
+}
 
-    if only_ui {
-        command.env("ONLY_UI", "true");
+// the stdin of whichever child is currently running, it changes on every restart
+type SharedStdin = Arc<Mutex<Option<ChildStdin>>>;
+
+fn write_child_stdin(child_stdin: &SharedStdin, bytes: &[u8]) {
+    let mut child_stdin = child_stdin.lock().unwrap();
+    let Some(stdin) = child_stdin.as_mut() else { return };
+    if stdin.write_all(bytes).and_then(|_| stdin.flush()).is_err() {
+        eprintln!("couldn't write to the game's stdin");
     }
-    println!("command created");
-    command
 }
 
-pub fn spawn_child_with_std_out_err_channel(mut command: Command) -> (Receiver<u8>, Child) {
-    command.stderr(Stdio::piped());
-    command.stdout(Stdio::piped());
-    let mut child = command.spawn().unwrap();
-    let stdout = child.stdout.take().unwrap().bytes();
-    let stderr = child.stderr.take().unwrap().bytes();
-    let (tx, rx) = crossbeam_channel::unbounded();
-    let tx2 = tx.clone();
+// how long an exited game's connection gets to deliver what it sent last
+const HANG_UP_TIMEOUT: Duration = Duration::from_secs(2);
 
-    thread::spawn(move || {
-        for b in stdout {
-            if let Ok(b) = b {
-                print!("{}", char::from(b));
-                match tx.send(b) {
-                    Err(err) => eprintln!("child_std_thread error: {err}"),
-                    _ => (),
-                }
+// `None` is sent when an editor hangs up
+struct QuitCommands {
+    commands: Receiver<Option<WatcherCommand>>,
+    connected: Arc<AtomicUsize>,
+}
+
+impl QuitCommands {
+    fn drain(&self) {
+        self.commands.try_iter().for_each(drop);
+    }
+
+    // the game's exit can beat its last command through the socket, so wait
+    // for every editor to hang up, everything they sent is in the channel then
+    fn last(&self) -> Option<WatcherCommand> {
+        let deadline = Instant::now() + HANG_UP_TIMEOUT;
+        let mut last = None;
+        loop {
+            let timeout = match self.connected.load(Ordering::SeqCst) {
+                0 => Duration::ZERO,
+                _ => deadline.saturating_duration_since(Instant::now()),
+            };
+            match self.commands.recv_timeout(timeout) {
+                Ok(Some(command)) => last = Some(command),
+                Ok(None) => {}
+                Err(_) => return last,
             }
         }
+    }
+}
+
+fn spawn_editor_message_thread(listener: TcpListener, child_stdin: SharedStdin, project_dir: PathBuf) -> QuitCommands {
+    let (quit_command_tx, quit_command_rx) = crossbeam_channel::unbounded();
+    let connected = Arc::new(AtomicUsize::new(0));
+    let counter = connected.clone();
+    thread::spawn(move || {
+        for rx_editor in listener.incoming() {
+            let Ok(rx_editor) = rx_editor else { continue };
+            let quit_command_tx = quit_command_tx.clone();
+            let child_stdin = child_stdin.clone();
+            let project_dir = project_dir.clone();
+            let counter = counter.clone();
+            counter.fetch_add(1, Ordering::SeqCst);
+            thread::spawn(move || {
+                read_editor_messages(rx_editor, &quit_command_tx, child_stdin, project_dir);
+                counter.fetch_sub(1, Ordering::SeqCst);
+                let _ = quit_command_tx.send(None);
+            });
+        }
     });
+    QuitCommands { commands: quit_command_rx, connected }
+}
+
+fn read_editor_messages(mut rx_editor: TcpStream, quit_command_tx: &Sender<Option<WatcherCommand>>, child_stdin: SharedStdin, project_dir: PathBuf) {
+    while let Ok(message) = EditorToWatcher::read_from(&mut rx_editor) {
+        match message {
+            EditorToWatcher::Command(WatcherCommand::Rebuild) => trigger_rebuild(&project_dir),
+            EditorToWatcher::Command(command) => {
+                let Ok(_) = quit_command_tx.send(Some(command)) else { return };
+            }
+            EditorToWatcher::Stdin(bytes) => write_child_stdin(&child_stdin, &bytes),
+        }
+    }
+}
+
+// dexterous_developer rebuilds and reloads whenever the sources change, so make them change
+fn trigger_rebuild(project_dir: &Path) {
+    let lib = project_dir.join("src").join("lib.rs");
+    let touched = std::fs::File::options()
+        .write(true)
+        .open(&lib)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+    if let Err(err) = touched {
+        eprintln!("couldn't trigger a rebuild by touching {}: {err}", lib.display());
+        return;
+    }
+    println!("rebuild requested from the editor");
+}
+
+// whatever the project manager types into its terminal goes to the game too
+fn spawn_own_stdin_thread(child_stdin: SharedStdin) {
     thread::spawn(move || {
-        for b in stderr {
-            if let Ok(b) = b {
-                print!("{}", char::from(b));
-                match tx2.send(b) {
-                    Err(err) => eprintln!("child_std_thread error: {err}"),
-                    _ => (),
-                }
+        let mut stdin = std::io::stdin();
+        let mut buf = [0; 1024];
+        while let Ok(read) = stdin.read(&mut buf) {
+            if read == 0 {
+                return;
             }
+            write_child_stdin(&child_stdin, &buf[..read]);
         }
     });
+}
+
+enum QuitType {
+    Clean,
+    Unclean,
+    /// The game (or its build) failed on its own, with this exit status.
+    Failed(String),
+}
+
+fn create_child_process(only_ui: bool, failure_report: Option<PathBuf>, location_to_run: String, rx_port: u16, tx_port: u16) -> Command {
+    let mut command = std::process::Command::new("dexterous_developer_cli");
+    command.arg("run");
+    command.env(TX_PORT, rx_port.to_string());
+    command.env(RX_PORT, tx_port.to_string());
+    command.current_dir(location_to_run);
 
-    (rx, child)
+    if only_ui {
+        command.env("ONLY_UI", "true");
+    }
+    if let Some(failure_report) = failure_report {
+        command.env(FAILURE_REPORT, failure_report);
+    }
+    println!("command created");
+    command
 }